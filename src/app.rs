@@ -1,30 +1,59 @@
 use std::{
+    collections::HashMap,
     env,
-    ffi::{CStr, CString},
-    fs, io, ptr,
+    ffi::{CString, OsStr, c_char},
+    fs::{self, File},
+    io::{self, Read, Write},
+    iter, mem,
+    ops::Range,
+    os::{
+        fd::FromRawFd,
+        unix::{
+            ffi::{OsStrExt, OsStringExt},
+            fs::PermissionsExt,
+        },
+    },
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
+    ptr,
+    time::{Duration, Instant},
 };
 
-use ansi_to_tui::IntoText;
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
 use ratatui::crossterm::{
     event::{
-        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseButton,
-        MouseEventKind,
+        DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        MouseButton, MouseEventKind,
     },
-    execute,
-    terminal::{self, Clear, ClearType},
+    execute, terminal,
 };
 use ratatui::{
     Frame, Terminal,
-    layout::{Alignment, Constraint, Layout},
+    layout::{Alignment, Constraint, Layout, Margin, Rect},
     prelude::Backend,
-    style::Style,
-    widgets::{Block, Borders, Paragraph},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
+    },
 };
-use strip_ansi_escapes::strip_str;
 use tui_input::{Input, backend::crossterm::EventHandler};
+use unicode_width::UnicodeWidthStr;
 
-use crate::{ManPageInfo, text_handling};
+use crate::{
+    ManPageInfo,
+    args::{Args, NO_COLOR_FLAG, NO_MOUSE_FLAG, PAGE_REF_FLAG, PARENT_FLAG, SUBSEQUENT_RUN_FLAG},
+    clipboard::Clipboard,
+    config::{Action, Config},
+    event_source::EventSource,
+    man_page_info::TRAILING_PUNCTUATION,
+    page::Page,
+    page_cache::PageCache,
+    state::State,
+    terminal_guard,
+    text_handling::{self, LineSpan},
+};
 
 /* TODO: Finish moving from the giant `run` function to this App struct, whose fields will have the
  * mutable app state and whose impl methods will do individual pieces of what the ungodly-big `run`
@@ -34,36 +63,323 @@ use crate::{ManPageInfo, text_handling};
 #[derive(Default, Debug)]
 /// Struct to store app state
 pub struct App {
-    content: String,
+    page: Page,
+    page_id: String,
+    parents: Vec<String>,
+    nested_flags: Vec<&'static str>,
     title: String,
-    lines: Vec<String>,
-    processed_content: String,
-    num_lines: u16,
-    scroll: u16,
-    height: u16,
+    wrap_enabled: bool,
+    headings: Vec<usize>,
+    synopsis: Range<usize>,
+    show_toc: bool,
+    show_line_numbers: bool,
+    relative_line_numbers: bool,
+    toc_selected: usize,
+    page_chooser: Option<PageChooser>,
+    link_hints: Option<LinkHints>,
+    content_x: u16,
+    scroll: usize,
+    hscroll: u16,
+    height: usize,
+    terminal_size: (u16, u16),
+    content_width: u16,
     mouse_mode: MouseMode,
-    search_input: Input,
-    search_mode: SearchMode,
+    input: Input,
+    input_mode: InputMode,
+    search_query: String,
+    search_history: Vec<String>,
+    search_history_index: Option<usize>,
+    search_draft: String,
+    search_case_insensitive: bool,
+    search_case_overridden: bool,
+    search_section: Option<Range<usize>>,
+    pre_search_scroll: usize,
+    search_matches: Vec<(usize, Range<usize>)>,
+    current_match: Option<usize>,
+    /// Where the selected match starts in the page's own (unwrapped) lines (see
+    /// [`App::unwrapped_position`]), so it stays selected when the page is laid out again
+    current_match_position: Option<(usize, usize)>,
+    selected_link: Option<(usize, Range<usize>)>,
+    hovered_link: Option<(usize, Range<usize>)>,
+    show_help: bool,
+    mouse_position: Option<(u16, u16)>,
+    pending_count: Option<usize>,
+    pending_mark: Option<MarkCommand>,
+    /// When a key bound to [`Action::Top`] was last pressed, if it was the last key pressed, so a
+    /// second press can complete vim's `gg` rather than jumping again
+    pending_top: Option<Instant>,
+    marks: HashMap<char, usize>,
+    previous_position: Option<usize>,
+    link_jump_failed: bool,
+    pending_overwrite: Option<PathBuf>,
+    page_cache: PageCache,
+    in_process: bool,
+    /// Whether to show the page without color (see [`crate::args::Args::no_color`]), setting things
+    /// apart with bold, underline, and reverse video instead
+    no_color: bool,
+    /// Preferences toggled at runtime, to remember for later runs
+    state: State,
+    /// What's in the state file, as far as we know, so it's only written when [`App::state`]
+    /// changes (and so doesn't undo changes a nested `linkman` made to it)
+    saved_state: State,
+    back_stack: Vec<VisitedPage>,
+    forward_stack: Vec<VisitedPage>,
+    last_click: Option<(Instant, u16, u16)>,
+    clipboard: Clipboard,
+    /// Message shown on the status line, and when it was set (it's cleared after
+    /// [`STATUS_MESSAGE_DURATION`])
+    status_message: Option<(String, Instant)>,
+    config: Config,
 }
 
 impl App {
-    pub(crate) fn new(content: String, man_page_id: impl AsRef<str>) -> Self {
-        let title = format!("LinkMan - {}", man_page_id.as_ref());
-        let lines: Vec<String> = strip_str(&content).lines().map(|s| s.to_owned()).collect();
-        let processed_content = lines.join("\n");
-        let num_lines = lines.len() as u16;
+    /// Creates an [`App`] displaying `content`, the man page identified by `man_page_id`, which was
+    /// reached by link jumps through the parents given in `args` (most recent first).
+    pub(crate) fn new(
+        content: String,
+        man_page_id: impl Into<String>,
+        args: Args,
+        config: Config,
+    ) -> Result<Self> {
+        Self::with_state(content, man_page_id, args, config, State::load())
+    }
 
-        Self {
-            content,
-            title,
-            lines,
-            processed_content,
-            num_lines,
+    /// Creates an [`App`] like [`App::new`] does, but with the remembered preferences given (or
+    /// the error loading them) rather than read from the state file.
+    fn with_state(
+        content: String,
+        man_page_id: impl Into<String>,
+        args: Args,
+        config: Config,
+        state: Result<State>,
+    ) -> Result<Self> {
+        let (state, state_error) = match state {
+            Ok(state) => (state, None),
+            Err(e) => (State::default(), Some(e)),
+        };
+        let mouse_mode = if args.no_mouse || !state.mouse_capture {
+            MouseMode::TextSelection
+        } else {
+            MouseMode::LinkClicking
+        };
+
+        // Only the first page needs to say this, rather than every page jumped to
+        let status_message = if !args.parents.is_empty() {
+            None
+        } else if let Some(e) = state_error {
+            Some(format!("Not using remembered preferences: {e}"))
+        } else if matches!(mouse_mode, MouseMode::TextSelection) {
+            Some(String::from(
+                "Mouse capture is off: Tab / Shift+Tab select links and Enter follows them (Alt+I \
+                 turns clicking on)",
+            ))
+        } else if args.no_alt_screen {
+            Some(String::from(
+                "Not using the alternate screen: mouse support varies by terminal here, but Tab \
+                 and Enter always follow links",
+            ))
+        } else {
+            None
+        };
+
+        // See https://no-color.org
+        let no_color = args.no_color
+            || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+            || !state.color;
+
+        let mut app = Self {
+            page: Page::new(content, None, no_color)?,
+            page_id: man_page_id.into(),
+            status_message: status_message.map(|message| (message, Instant::now())),
+            nested_flags: args.nested_flags(),
+            in_process: args.in_process,
+            no_color,
+            mouse_mode,
+            parents: args.parents,
+            config,
+            wrap_enabled: state.wrap,
+            saved_state: state.clone(),
+            state,
             ..Default::default()
+        };
+        app.update_title();
+        app.page_laid_out();
+
+        Ok(app)
+    }
+
+    /// Shows `message` on the status line, until it's replaced or cleared or
+    /// [`STATUS_MESSAGE_DURATION`] passes.
+    fn set_status(&mut self, message: String) {
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    /// Sets the title to the page's [`App::history`].
+    fn update_title(&mut self) {
+        self.title = format!("LinkMan - {}", self.history().join(" ← "));
+    }
+
+    /// Returns the identifiers of this page and the pages jumped through to reach it (whether in
+    /// this process or by nesting), most recent first.
+    fn history(&self) -> Vec<&str> {
+        iter::once(&self.page_id)
+            .chain(self.back_stack.iter().rev().map(|page| &page.page_id))
+            .chain(&self.parents)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Returns the flags to pass to a nested `linkman`: the ones we were given, but with the
+    /// current [`App::mouse_mode`] and colors (which may have been toggled since) in place of
+    /// `--no-mouse` and `--no-color`.
+    fn jump_flags(&self) -> Vec<&'static str> {
+        self.nested_flags
+            .iter()
+            .copied()
+            .filter(|&flag| flag != NO_MOUSE_FLAG && flag != NO_COLOR_FLAG)
+            .chain(matches!(self.mouse_mode, MouseMode::TextSelection).then_some(NO_MOUSE_FLAG))
+            .chain(self.no_color.then_some(NO_COLOR_FLAG))
+            .collect()
+    }
+
+    /// Writes [`App::state`] to the state file if it's changed since it was last written. This is
+    /// best-effort, so failing to write it is ignored.
+    fn save_state(&mut self) {
+        if self.state != self.saved_state {
+            drop(self.state.save());
+            self.saved_state = self.state.clone();
+        }
+    }
+
+    /// Returns whether quitting will go back to a previous page rather than exit `linkman`
+    /// altogether.
+    fn can_go_back(&self) -> bool {
+        self.is_nested() || !self.back_stack.is_empty()
+    }
+
+    /// Displays `page` in place of the current page, returning the current page (with its position)
+    /// so it can be returned to.
+    fn swap_page(&mut self, page: VisitedPage) -> Result<VisitedPage> {
+        let previous = VisitedPage {
+            page_id: mem::replace(&mut self.page_id, page.page_id),
+            content: self.page.replace_content(page.content)?,
+            scroll: mem::replace(&mut self.scroll, page.scroll),
+            marks: mem::replace(&mut self.marks, page.marks),
+            previous_position: mem::replace(&mut self.previous_position, page.previous_position),
+        };
+        self.hscroll = 0;
+        self.toc_selected = 0;
+        // The selected match was in the page swapped out
+        self.current_match_position = None;
+        self.page_laid_out();
+        // The new page may need a wider gutter for its line numbers
+        self.fit_wrap()?;
+
+        Ok(previous)
+    }
+
+    /// Returns to the page an in-process link jump was made from (see [`App::follow_link`]), so it
+    /// can be gone forward to again.
+    fn go_back(&mut self) -> Result<()> {
+        if let Some(page) = self.back_stack.pop() {
+            let next = self.swap_page(page)?;
+            self.forward_stack.push(next);
+            self.update_title();
         }
+
+        Ok(())
+    }
+
+    /// Returns to the page most recently gone back from (see [`App::go_back`]).
+    fn go_forward(&mut self) -> Result<()> {
+        if !self.in_process {
+            self.set_status(String::from(
+                "Going forward needs --in-process, since closed pages aren't kept otherwise",
+            ));
+        } else if let Some(page) = self.forward_stack.pop() {
+            let previous = self.swap_page(page)?;
+            self.back_stack.push(previous);
+            self.update_title();
+        } else {
+            self.set_status(String::from("No page to go forward to"));
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether this page was opened by a link jump from another page (which quitting will
+    /// return to).
+    fn is_nested(&self) -> bool {
+        !self.parents.is_empty()
+    }
+
+    /// Refreshes everything derived from [`App::page`]'s lines, after it's laid out anew (e.g.
+    /// rewrapped, or replaced by another page).
+    fn page_laid_out(&mut self) {
+        self.headings = text_handling::find_headings(self.page.lines());
+        self.synopsis = self.find_synopsis();
+        self.selected_link = None;
+        self.hovered_link = None;
+        // The links labeled may have moved
+        self.link_hints = None;
+
+        // Matches (and the section a search is limited to) move along with the lines they're in
+        if self.search_section.is_some() {
+            self.search_section = Some(self.section_around(self.scroll));
+        }
+        self.search_matches = self.find_search_matches();
+        self.current_match = self.current_match_position.and_then(|position| {
+            let unwrapped_lines = self.unwrapped_line_indices();
+            self.search_matches
+                .iter()
+                .position(|&(line_index, Range { start, .. })| {
+                    (
+                        unwrapped_lines[line_index],
+                        self.page.line_starts()[line_index] + start,
+                    ) == position
+                })
+        });
+        if self.current_match.is_none() {
+            self.current_match_position = None;
+        }
+    }
+
+    /// Returns the index of the page's own (unwrapped) line that each of [`App::page`]'s lines was
+    /// soft-wrapped from.
+    fn unwrapped_line_indices(&self) -> Vec<usize> {
+        self.page
+            .line_starts()
+            .iter()
+            .scan(0, |unwrapped_line, &start| {
+                if start == 0 {
+                    *unwrapped_line += 1;
+                }
+                Some(*unwrapped_line - 1)
+            })
+            .collect()
+    }
+
+    /// Returns where `(line_index, offset)` in [`App::page`]'s lines is in the page's own
+    /// (unwrapped) lines, as the index of that line and the byte offset in it. Unlike a position
+    /// in the laid out lines, this doesn't change when the page is rewrapped.
+    fn unwrapped_position(&self, (line_index, offset): (usize, usize)) -> (usize, usize) {
+        let line_starts = self.page.line_starts();
+        let unwrapped_line = line_starts[..=line_index]
+            .iter()
+            .filter(|&&start| start == 0)
+            .count()
+            .saturating_sub(1);
+
+        (unwrapped_line, line_starts[line_index] + offset)
     }
 
-    pub(crate) fn run<B>(mut self, terminal: &mut Terminal<B>) -> Result<()>
+    /// Runs the TUI, handling input from `events`, until the user quits, returning the status
+    /// `linkman` should exit with (see [`EXIT_LINK_JUMP_FAILED`]).
+    pub(crate) fn run<B>(
+        mut self,
+        terminal: &mut Terminal<B>,
+        events: &mut impl EventSource,
+    ) -> Result<ExitCode>
     where
         B: ratatui::backend::Backend,
     {
@@ -71,215 +387,2170 @@ impl App {
         // so this is safe.
         unsafe { set_man_width_variable()? };
 
-        let mut stdout = io::stdout();
+        // The terminal starts out capturing the mouse, which `--no-mouse` turns off
+        self.apply_mouse_mode()?;
 
-        execute!(
-            stdout,
-            Clear(ClearType::All),
-            EnableMouseCapture, // Starting in MouseMode::LinkClicking
-        )?;
-
-        // Register panic handler to disable mouse capture
-        let old_panic_hook = std::panic::take_hook();
-        std::panic::set_hook(Box::new(move |hook_info| {
-            drop(execute!(io::stdout(), DisableMouseCapture));
-            old_panic_hook(hook_info);
-        }));
+        self.event_loop(terminal, events)?;
 
-        loop {
-            terminal.draw(|frame| self.render(frame))?;
+        self.save_state();
+
+        // Nested instances exit successfully regardless, since their failed jumps were already
+        // reported (on their own status line), and a failure status would make the page that
+        // jumped to them report its jump as failed too
+        Ok(if self.link_jump_failed && !self.is_nested() {
+            ExitCode::from(EXIT_LINK_JUMP_FAILED)
+        } else {
+            ExitCode::SUCCESS
+        })
+    }
+
+    /// Draws the page and handles input from `events`, until the user quits.
+    fn event_loop<B>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        events: &mut impl EventSource,
+    ) -> Result<()>
+    where
+        B: Backend,
+    {
+        // Lay the page out for the terminal before it's first drawn
+        let size = terminal.size()?;
+        self.handle_resize(size.width, size.height)?;
 
-            if !self.handle_event(terminal)? {
-                break;
+        let mut redraw = true;
+        loop {
+            if redraw {
+                terminal.draw(|frame| self.render(frame))?;
             }
-        }
 
-        execute!(stdout, DisableMouseCapture)?;
+            // Wait for input with a timeout rather than indefinitely, so time-based updates (like
+            // a resize that isn't reported as an event) happen while idle. Polling keeps
+            // everything on this one thread, rather than needing a SIGWINCH handler that would run
+            // alongside `set_man_width_variable`'s environment changes.
+            let event = events.next_event(TICK_INTERVAL)?;
+            redraw = self.tick(terminal)?;
+            let Some(event) = event else {
+                continue;
+            };
 
-        Ok(())
+            if !self.handle_event(terminal, event)? {
+                return Ok(());
+            }
+
+            // The pointer may be over a different word after any event (e.g. scrolling)
+            self.update_hovered_link();
+            redraw = true;
+        }
     }
 
     fn render(&mut self, frame: &mut Frame) {
-        const SEARCH_PREFIX: &str = "Search: ";
-        const SEARCH_PREFIX_LEN: u16 = SEARCH_PREFIX.len() as u16;
-
         let area = frame.area();
-        self.height = area.height;
-        self.scroll = self
-            .scroll
-            .min(self.num_lines.saturating_sub(self.height) + 2);
+        self.height = area.height as usize;
+        self.terminal_size = (area.width, area.height);
+        self.scroll = self.scroll.min(self.max_scroll());
+
+        // There's no room for the borders and the status line, let alone the page
+        if area.width < MIN_TERMINAL_SIZE || area.height < MIN_TERMINAL_SIZE {
+            frame.render_widget(
+                Paragraph::new("Terminal too small").wrap(Wrap { trim: true }),
+                area,
+            );
+            return;
+        }
 
         // Split screen vertically into space for the content, and a single line for commands/searching
-        let chunks = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(area);
+        let mut chunks = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)])
+            .split(area)
+            .to_vec();
 
-        // Make content Paragraph
-        let content_paragraph = Paragraph::new(
-            self.processed_content
-                .into_text()
-                .expect("ansi_to_tui IntoText::into_text call failed"),
-        )
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(self.title.as_str())
-                .title_alignment(Alignment::Center),
-        )
-        .style(Style::default())
-        .scroll((self.scroll, 0));
+        // If the table of contents is open, split it off the left of the content area
+        if self.show_toc {
+            let [toc_area, content_area] =
+                Layout::horizontal([Constraint::Length(self.toc_width()), Constraint::Fill(1)])
+                    .areas(chunks[0]);
+            self.render_toc(frame, toc_area);
+            chunks[0] = content_area;
+        }
+
+        // If line numbers are on, split a gutter for them off the left of the content area
+        if self.show_line_numbers {
+            let [gutter_area, content_area] =
+                Layout::horizontal([Constraint::Length(self.gutter_width()), Constraint::Fill(1)])
+                    .areas(chunks[0]);
+            self.render_line_numbers(frame, gutter_area);
+            chunks[0] = content_area;
+        }
+        self.content_x = chunks[0].x;
+        self.content_width = chunks[0].width.saturating_sub(2);
+        self.hscroll = self.hscroll.min(self.max_hscroll());
+
+        // Only the lines on screen are drawn (and scrolled to by slicing rather than by the
+        // `Paragraph`, whose scroll offset can't reach past `u16::MAX` lines), so `text.lines[i]`
+        // is line `first_line + i` of the page
+        let visible_lines = self.visible_lines();
+        let first_line = visible_lines.start;
+        let mut text = Text::from(self.page.text().lines[visible_lines.clone()].to_vec())
+            .style(self.page.text().style);
+
+        let colors = (!self.no_color).then_some(&self.config.colors);
+
+        // Set the SYNOPSIS apart, since it's what's most often looked for. This is a line style, so
+        // it's under any styles the page itself gives its text.
+        if self.config.highlight_synopsis
+            && let Some(colors) = colors
+        {
+            for line_index in
+                self.synopsis.start.max(first_line)..self.synopsis.end.min(visible_lines.end)
+            {
+                let line = &mut text.lines[line_index - first_line];
+                line.style = line.style.fg(colors.synopsis);
+            }
+        }
+
+        if let Some(color) = colors.and_then(|colors| colors.heading) {
+            for &line_index in &self.headings {
+                if let Some(line) = line_index
+                    .checked_sub(first_line)
+                    .and_then(|i| text.lines.get_mut(i))
+                {
+                    line.style = line.style.fg(color);
+                }
+            }
+        }
+
+        // Underline every man reference on screen so it's clear what can be clicked
+        let link_style = colors
+            .map_or_else(Style::new, |colors| Style::new().fg(colors.link))
+            .underlined();
+        for (line_index, range) in self.visible_links() {
+            text_handling::patch_line_style(
+                &mut text.lines[line_index - first_line],
+                range,
+                link_style,
+            );
+        }
+
+        // Highlight search matches, but only bother with those on visible lines
+        for (match_index, &(line_index, Range { start, end })) in
+            self.search_matches.iter().enumerate()
+        {
+            // A match can carry on across the lines wrapped from the same line of the page, so
+            // work in offsets into that line
+            let start = self.page.line_starts()[line_index] + start;
+            let end = self.page.line_starts()[line_index] + end;
+            let parts = line_index
+                ..(line_index + 1)
+                    + self.page.line_starts()[line_index + 1..]
+                        .iter()
+                        .take_while(|&&part_start| part_start != 0 && part_start < end)
+                        .count();
+            if parts.end <= visible_lines.start || parts.start >= visible_lines.end {
+                continue;
+            }
+
+            let style = if self.current_match == Some(match_index) {
+                colors.map_or_else(
+                    || Style::new().reversed().bold().underlined(),
+                    |colors| Style::new().black().bg(colors.search_match).bold(),
+                )
+            } else if let Some(color) = colors.and_then(|colors| colors.matches) {
+                Style::new().black().bg(color)
+            } else {
+                Style::new().reversed()
+            };
+            for part in parts.start.max(first_line)..parts.end.min(visible_lines.end) {
+                let part_start = self.page.line_starts()[part];
+                text_handling::patch_line_style(
+                    &mut text.lines[part - first_line],
+                    start.saturating_sub(part_start)..end - part_start,
+                    style,
+                );
+            }
+        }
+
+        // Highlight the link under the mouse pointer, so it's clear what clicking will do
+        if let Some((line_index, range)) = &self.hovered_link
+            && let Some(line) = line_index
+                .checked_sub(first_line)
+                .and_then(|i| text.lines.get_mut(i))
+        {
+            text_handling::patch_line_style(line, range.clone(), Style::new().bold().reversed());
+        }
+
+        // Highlight the link selected with Tab/Shift+Tab
+        if let Some((line_index, range)) = &self.selected_link
+            && let Some(line) = line_index
+                .checked_sub(first_line)
+                .and_then(|i| text.lines.get_mut(i))
+        {
+            text_handling::patch_line_style(
+                line,
+                range.clone(),
+                colors.map_or_else(
+                    || Style::new().reversed().underlined(),
+                    |colors| Style::new().black().bg(colors.selected_link),
+                ),
+            );
+        }
+
+        // Make content Paragraph, with a title that fits between the border's corners
+        let title =
+            text_handling::elide_middle(&self.title, (chunks[0].width as usize).saturating_sub(2));
+        let content_paragraph = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .title_alignment(Alignment::Center),
+            )
+            .style(Style::default())
+            .scroll((0, self.hscroll));
 
         frame.render_widget(content_paragraph, chunks[0]);
 
-        // If the user's typing a search query...
-        if self.search_mode == SearchMode::TypingQuery {
-            let input_text = format!("{}{}", SEARCH_PREFIX, self.search_input.value());
+        if let Some(hints) = &self.link_hints {
+            self.render_link_hints(frame, hints, chunks[0]);
+        }
+
+        // Draw a scrollbar over the right border (between the corners), if there's room for one
+        // (`Scrollbar` panics when there isn't, e.g. when the table of contents takes up the
+        // whole width)
+        let scrollbar_area = chunks[0].inner(Margin {
+            vertical: 1,
+            horizontal: 0,
+        });
+        if !scrollbar_area.is_empty() {
+            let mut scrollbar_state = ScrollbarState::new(self.max_scroll())
+                .position(self.scroll)
+                .viewport_content_length(self.content_height());
+            frame.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .begin_symbol(None)
+                    .end_symbol(None),
+                scrollbar_area,
+                &mut scrollbar_state,
+            );
+        }
+
+        // If the user's typing a search query, command, or filename...
+        if self.input_mode != InputMode::Normal {
+            let prompt = match self.input_mode {
+                InputMode::Search => {
+                    let qualifiers: Vec<&str> = [
+                        self.search_case_insensitive.then_some("ignore case"),
+                        self.search_section.is_some().then_some("this section"),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                    if qualifiers.is_empty() {
+                        String::from("Search: ")
+                    } else {
+                        format!("Search ({}): ", qualifiers.join(", "))
+                    }
+                }
+                InputMode::Save { formatted } => {
+                    let what = if formatted {
+                        "page (with formatting)"
+                    } else {
+                        "page"
+                    };
+                    if self.pending_overwrite.is_some() {
+                        format!("File exists, Enter again to overwrite it with the {what}: ")
+                    } else {
+                        format!("Save {what} as: ")
+                    }
+                }
+                InputMode::ConfirmQuit => String::from("Quit LinkMan? (y/n) "),
+                InputMode::Section { ref name } => format!("Section to open {name} from: "),
+                InputMode::Apropos => String::from("Find pages about: "),
+                _ => String::from(":"),
+            };
+            let input_text = format!("{}{}", prompt, self.input.value());
             let input_paragraph = Paragraph::new(input_text);
 
-            // Render typed query so far
+            // Render typed input so far
             frame.render_widget(input_paragraph, chunks[1]);
 
             // Set cursor position
-            let pos = self.search_input.visual_cursor() as u16;
-            frame.set_cursor_position((pos + SEARCH_PREFIX_LEN, area.height));
+            let pos = self.input.visual_cursor() as u16;
+            frame.set_cursor_position((pos + prompt.len() as u16, area.height));
+        } else {
+            let indicator = match self.match_indicator() {
+                Some(match_indicator) => {
+                    format!("{match_indicator}  {}", self.position_indicator())
+                }
+                None => self.position_indicator(),
+            };
+            frame.render_widget(
+                Paragraph::new(indicator).alignment(Alignment::Right),
+                chunks[1],
+            );
+
+            if let Some((message, _)) = &self.status_message {
+                let style = colors
+                    .and_then(|colors| colors.status)
+                    .map_or_else(Style::new, |color| Style::new().fg(color));
+                frame.render_widget(Paragraph::new(message.as_str()).style(style), chunks[1]);
+            } else if self.can_go_back() {
+                frame.render_widget(Paragraph::new("q: back to previous page").dim(), chunks[1]);
+            }
+        }
+
+        if let Some(chooser) = &self.page_chooser {
+            chooser.render(frame, area);
+        }
+
+        if self.show_help {
+            render_help(frame, area, &self.config);
+        }
+    }
+
+    /// Does what needs doing after every event, and every [`TICK_INTERVAL`] (or so) while there's
+    /// no input, returning whether anything on screen changed.
+    fn tick<B>(&mut self, terminal: &mut Terminal<B>) -> Result<bool>
+    where
+        B: Backend,
+    {
+        let mut changed = false;
+
+        let size = terminal.size()?;
+        if (size.width, size.height) != self.terminal_size {
+            self.handle_resize(size.width, size.height)?;
+            changed = true;
         }
+
+        if self
+            .status_message
+            .as_ref()
+            .is_some_and(|(_, shown)| shown.elapsed() >= STATUS_MESSAGE_DURATION)
+        {
+            self.status_message = None;
+            changed = true;
+        }
+
+        Ok(changed)
     }
 
-    fn handle_event<B>(&mut self, terminal: &mut Terminal<B>) -> Result<bool>
+    /// Handles an input event, returning whether to keep running.
+    fn handle_event<B>(&mut self, terminal: &mut Terminal<B>, event: Event) -> Result<bool>
     where
         B: Backend,
     {
-        if self.search_mode == SearchMode::TypingQuery {
-            match event::read()? {
-                Event::Key(key) if key.code == KeyCode::Enter => self.perform_search()?,
-                Event::Key(key) if key.code == KeyCode::Esc => self.cancel_search(),
-                non_enter_event => drop(self.search_input.handle_event(&non_enter_event)),
+        match self.input_mode {
+            InputMode::Normal => (),
+            InputMode::Search => {
+                self.handle_search_input(event)?;
+                return Ok(true);
+            }
+            InputMode::Command => {
+                self.handle_command_input(event);
+                return Ok(true);
+            }
+            InputMode::Save { formatted } => {
+                self.handle_save_input(event, formatted);
+                return Ok(true);
+            }
+            InputMode::ConfirmQuit => return Ok(!self.handle_quit_confirmation(event)),
+            InputMode::Section { .. } => {
+                self.handle_section_input(terminal, event)?;
+                return Ok(true);
+            }
+            InputMode::Apropos => {
+                self.handle_apropos_input(terminal, event)?;
+                return Ok(true);
+            }
+        }
+
+        // Any keypress dismisses the help overlay
+        if self.show_help && matches!(event, Event::Key(_)) {
+            self.show_help = false;
+            return Ok(true);
+        }
+
+        if let Event::Mouse(mouse_event) = &event {
+            self.mouse_position = Some((
+                mouse_event.column.saturating_sub(self.content_x),
+                mouse_event.row,
+            ));
+        }
+
+        // While choosing a page, it takes all keys
+        if self.page_chooser.is_some()
+            && let Event::Key(key) = event
+        {
+            self.handle_page_chooser_key(terminal, key)?;
+            return Ok(true);
+        }
+
+        // While links are labeled, the keys typed pick one (and other events are ignored, so the
+        // labels stay put)
+        if self.link_hints.is_some() {
+            if let Event::Key(key) = event {
+                self.handle_link_hint_key(terminal, key)?;
+            }
+            return Ok(true);
+        }
+
+        // While the table of contents is open, it takes movement keys and Enter
+        if self.show_toc
+            && let Event::Key(key) = event
+        {
+            match (self.config.keys.action(&key), key.code) {
+                (Some(Action::ScrollDown), _) | (_, KeyCode::Down) => {
+                    self.toc_selected =
+                        (self.toc_selected + 1).min(self.headings.len().saturating_sub(1));
+                    return Ok(true);
+                }
+                (Some(Action::ScrollUp), _) | (_, KeyCode::Up) => {
+                    self.toc_selected = self.toc_selected.saturating_sub(1);
+                    return Ok(true);
+                }
+                (_, KeyCode::Enter) => {
+                    if let Some(&line_index) = self.headings.get(self.toc_selected) {
+                        self.jump_to(line_index);
+                    }
+                    return Ok(true);
+                }
+                (_, KeyCode::Esc) => {
+                    self.show_toc = false;
+                    self.fit_wrap()?;
+                    return Ok(true);
+                }
+                _ => (),
+            }
+        }
+
+        // The key after `m` or a backtick names a mark
+        if let Event::Key(key) = event
+            && let Some(mark_command) = self.pending_mark.take()
+        {
+            match (mark_command, key.code) {
+                (_, KeyCode::Char(letter)) if letter.is_ascii_alphabetic() => {
+                    self.use_mark(mark_command, letter)
+                }
+                // Two backticks or apostrophes, as in vim
+                (MarkCommand::Jump, KeyCode::Char('\'' | '`')) => self.jump_back(),
+                _ => (),
+            }
+            return Ok(true);
+        }
+
+        if let Event::Key(key) = event {
+            // Digits build up a count for the next movement (e.g. `10j`), though a leading `0` is
+            // left to its own binding
+            if let KeyCode::Char(digit @ '0'..='9') = key.code
+                && key.modifiers.is_empty()
+                && (digit != '0' || self.pending_count.is_some())
+            {
+                let digit = digit.to_digit(10).expect("matched a decimal digit") as usize;
+                self.pending_count = Some(
+                    self.pending_count
+                        .unwrap_or(0)
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
+                return Ok(true);
+            }
+
+            // Any other key uses up the count, whether or not it's a movement
+            let count = self.pending_count.take();
+            let pending_top = self.pending_top.take();
+
+            // Configurable keys take precedence over the fixed ones below
+            if let Some(action) = self.config.keys.action(&key) {
+                if action == Action::Top {
+                    // The first `g` of `gg` (or `Ngg`) already made the jump
+                    if count.is_none()
+                        && pending_top.is_some_and(|time| time.elapsed() <= KEY_SEQUENCE_TIMEOUT)
+                    {
+                        return Ok(true);
+                    }
+                    self.pending_top = Some(Instant::now());
+                }
+                return self.perform_action(action, count);
+            }
+        }
+
+        match event {
+            Event::Key(key) => match (key.code, key.modifiers) {
+                (KeyCode::Char('i'), KeyModifiers::ALT) => self.toggle_mouse_mode()?,
+                (KeyCode::Char('n'), KeyModifiers::NONE) => self.next_match(),
+                (KeyCode::Char('N'), _) | (KeyCode::Char('n'), KeyModifiers::SHIFT) => {
+                    self.previous_match()
+                }
+                (KeyCode::Char('t'), KeyModifiers::NONE) => self.toggle_toc()?,
+                (KeyCode::Char('f'), KeyModifiers::NONE) => self.show_link_hints(),
+                (KeyCode::Char('w'), KeyModifiers::NONE) => self.toggle_wrap()?,
+                (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
+                    self.show_line_numbers = !self.show_line_numbers;
+                    self.fit_wrap()?;
+                }
+                (KeyCode::Char('n'), KeyModifiers::ALT) => {
+                    // Relative numbers are only useful if they're shown
+                    self.relative_line_numbers = !self.relative_line_numbers;
+                    self.show_line_numbers |= self.relative_line_numbers;
+                    self.fit_wrap()?;
+                }
+                (KeyCode::Char('m'), KeyModifiers::NONE) => {
+                    self.pending_mark = Some(MarkCommand::Set)
+                }
+                (KeyCode::Char('`' | '\''), _) => self.pending_mark = Some(MarkCommand::Jump),
+                (KeyCode::Char(']'), _) => self.next_heading(),
+                (KeyCode::Char('['), _) => self.previous_heading(),
+                (KeyCode::Char('?'), _) => self.show_help = true,
+                (KeyCode::Char('s'), KeyModifiers::NONE) => self.start_save(false),
+                (KeyCode::Char('S'), _) | (KeyCode::Char('s'), KeyModifiers::SHIFT) => {
+                    self.start_save(true)
+                }
+                (KeyCode::Char('|'), _) => self.open_in_pager(terminal)?,
+                (KeyCode::Char(':'), _) => {
+                    self.status_message = None;
+                    self.input_mode = InputMode::Command;
+                }
+                (KeyCode::Char('y'), KeyModifiers::NONE) => self.copy_link(),
+                (KeyCode::Char('Y'), _) | (KeyCode::Char('y'), KeyModifiers::SHIFT) => {
+                    self.copy_text(false)
+                }
+                (KeyCode::Char('y'), KeyModifiers::ALT) => self.copy_text(true),
+                (KeyCode::Char('c'), KeyModifiers::NONE) => self.copy_man_command(),
+                (KeyCode::Char('r'), KeyModifiers::NONE) => self.reload()?,
+                (KeyCode::Char('e'), KeyModifiers::NONE) => self.edit_source(terminal)?,
+                (KeyCode::Char('C'), _) | (KeyCode::Char('c'), KeyModifiers::SHIFT) => {
+                    self.toggle_colors()?
+                }
+                (KeyCode::Char('a'), KeyModifiers::NONE) => {
+                    self.status_message = None;
+                    self.input_mode = InputMode::Apropos;
+                }
+                (KeyCode::Tab, _) => self.select_link(true),
+                (KeyCode::BackTab, _) => self.select_link(false),
+                (KeyCode::Enter, _) => {
+                    let selected_word = self
+                        .selected_link
+                        .as_ref()
+                        .and_then(|(line_index, range)| {
+                            self.page.lines()[*line_index].get(range.clone())
+                        })
+                        .map(str::to_owned);
+                    if let Some(word) = selected_word
+                        && let Ok(info) = ManPageInfo::try_from(word.as_str())
+                    {
+                        self.follow_link(terminal, &info)?;
+                    }
+                }
+                (KeyCode::Esc, _) | (KeyCode::Char('l'), KeyModifiers::CONTROL)
+                    if !self.search_query.is_empty() =>
+                {
+                    self.clear_search()
+                }
+                _ => (),
+            },
+            Event::Mouse(mouse_event)
+                if matches!(mouse_event.kind, MouseEventKind::Up(MouseButton::Left))
+                    && (1..=self.content_height()).contains(&(mouse_event.row as usize)) =>
+            {
+                let row = mouse_event.row as usize;
+                let column = mouse_event.column.saturating_sub(self.content_x) as usize
+                    + self.hscroll as usize;
+
+                let is_double_click = self.last_click.is_some_and(|(time, column, row)| {
+                    time.elapsed() <= DOUBLE_CLICK_INTERVAL
+                        && (column, row) == (mouse_event.column, mouse_event.row)
+                });
+                // A third click starts over rather than counting as another double click
+                self.last_click = (!is_double_click)
+                    .then(|| (Instant::now(), mouse_event.column, mouse_event.row));
+
+                // A section named in the text (e.g. "see OPTIONS below") is in this page
+                if let Some(line_index) = self.heading_named_at(row, column) {
+                    self.jump_to(line_index);
+                    return Ok(true);
+                }
+
+                // Ignoring failures (user probably just clicked on something that wasn't a link)
+                let man_refs = text_handling::man_refs_at_position(
+                    self.page.lines(),
+                    self.scroll,
+                    row,
+                    column,
+                );
+                if let Some(info) = self.choose_man_ref(&man_refs) {
+                    self.follow_link(terminal, &info)?;
+                } else if let Some(url) = text_handling::external_link_at_position(
+                    self.page.lines(),
+                    self.scroll,
+                    row,
+                    column,
+                ) {
+                    self.open_external(&url);
+                } else if is_double_click
+                    && let Some(word) =
+                        text_handling::word_at_position(self.page.lines(), self.scroll, row, column)
+                            .map(|word| word.trim_end_matches(TRAILING_PUNCTUATION).to_owned())
+                            .filter(|word| !word.is_empty())
+                {
+                    // Alt asks which section to open it from, rather than leaving that to `man`
+                    if mouse_event.modifiers.contains(KeyModifiers::ALT) {
+                        self.status_message = None;
+                        self.input_mode = InputMode::Section { name: word };
+                    } else {
+                        self.open_bare_name(terminal, word)?;
+                    }
+                }
+            }
+            // Many terminals report Shift+wheel as horizontal scrolling on their own, but some pass
+            // it through as vertical scrolling with the Shift modifier
+            Event::Mouse(mouse_event)
+                if mouse_event.kind == MouseEventKind::ScrollRight
+                    || (mouse_event.kind == MouseEventKind::ScrollDown
+                        && mouse_event.modifiers.contains(KeyModifiers::SHIFT)) =>
+            {
+                self.scroll_right(HSCROLL_STEP)?;
+            }
+            Event::Mouse(mouse_event)
+                if mouse_event.kind == MouseEventKind::ScrollLeft
+                    || (mouse_event.kind == MouseEventKind::ScrollUp
+                        && mouse_event.modifiers.contains(KeyModifiers::SHIFT)) =>
+            {
+                self.scroll_left(HSCROLL_STEP);
+            }
+            Event::Mouse(mouse_event) if mouse_event.kind == MouseEventKind::ScrollDown => {
+                self.scroll_down(self.config.scroll_lines_per_wheel);
+            }
+            Event::Mouse(mouse_event) if mouse_event.kind == MouseEventKind::ScrollUp => {
+                self.scroll_up(self.config.scroll_lines_per_wheel);
+            }
+            Event::Resize(cols, rows) => self.handle_resize(cols, rows)?,
+            _ => (),
+        }
+
+        Ok(true)
+    }
+
+    /// Recalculates everything that depends on the terminal's size after it's resized to `cols` by
+    /// `rows`.
+    fn handle_resize(&mut self, cols: u16, rows: u16) -> Result<()> {
+        // Don't wait for the next draw to update the size, so the wrap width and scroll clamp are
+        // right for any events handled before then
+        self.height = rows as usize;
+        self.terminal_size = (cols, rows);
+
+        self.fit_wrap()?;
+
+        self.scroll = self.scroll.min(self.max_scroll());
+
+        // `MANWIDTH` isn't updated here: `run` already set it, and `set_man_width_variable` leaves
+        // it as it is once it's set
+        Ok(())
+    }
+
+    /// Performs the action bound to a (configurable) key, returning whether to keep running.
+    ///
+    /// Movements are repeated `count` times, if a count was typed before the key. For [`Action::Top`]
+    /// and [`Action::Bottom`], the count is instead the line number to go to (like `5G` in vim).
+    fn perform_action(&mut self, action: Action, count: Option<usize>) -> Result<bool> {
+        let times = count.unwrap_or(1);
+        // Horizontal scrolling is by (far fewer) columns
+        let columns = HSCROLL_STEP.saturating_mul(times.try_into().unwrap_or(u16::MAX));
+        let page = self.content_height();
+
+        match action {
+            Action::Quit if !self.back_stack.is_empty() => self.go_back()?,
+            // Only quitting to the shell is worth asking about, not going back to a previous page
+            Action::Quit if self.config.confirm_quit && !self.is_nested() => {
+                self.status_message = None;
+                self.input_mode = InputMode::ConfirmQuit;
+            }
+            Action::Quit => return Ok(false),
+            Action::ScrollDown => self.scroll_down(times),
+            Action::ScrollUp => self.scroll_up(times),
+            Action::PageDown => self.scroll_down(page.saturating_mul(times)),
+            Action::PageUp => self.scroll_up(page.saturating_mul(times)),
+            Action::HalfPageDown => self.scroll_down((page / 2).saturating_mul(times)),
+            Action::HalfPageUp => self.scroll_up((page / 2).saturating_mul(times)),
+            Action::Top => self.go_to_line(times),
+            Action::Bottom => match count {
+                Some(line_number) => self.go_to_line(line_number),
+                None => self.jump_to(self.max_scroll()),
+            },
+            Action::ScrollLeft => self.scroll_left(columns),
+            Action::ScrollRight => self.scroll_right(columns)?,
+            Action::Search => self.start_search(),
+            // Without --in-process (or once back at its first page), the previous page is the
+            // `linkman` that nested this one, which exiting goes back to
+            Action::Back if self.is_nested() && self.back_stack.is_empty() => return Ok(false),
+            Action::Back if !self.can_go_back() => {
+                self.set_status(String::from("No previous page to go back to"))
+            }
+            Action::Back => self.go_back()?,
+            Action::Forward => self.go_forward()?,
+        }
+
+        Ok(true)
+    }
+
+    /// Handles an event while the user is typing a search query.
+    fn handle_search_input(&mut self, event: Event) -> Result<()> {
+        match event {
+            Event::Key(key) if key.code == KeyCode::Enter => self.perform_search()?,
+            Event::Key(key) if key.code == KeyCode::Esc => self.cancel_search(),
+            Event::Key(key)
+                if key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::ALT =>
+            {
+                self.search_case_insensitive = !self.search_case_insensitive;
+                self.search_case_overridden = true;
+                self.update_search();
+            }
+            Event::Key(key)
+                if key.code == KeyCode::Char('s') && key.modifiers == KeyModifiers::ALT =>
+            {
+                self.search_section = match self.search_section {
+                    Some(_) => None,
+                    None => Some(self.section_around(self.pre_search_scroll)),
+                };
+                self.update_search();
+            }
+            Event::Key(key) if key.code == KeyCode::Up => self.recall_older_search(),
+            Event::Key(key) if key.code == KeyCode::Down => self.recall_newer_search(),
+            non_enter_event => {
+                if self.input.handle_event(&non_enter_event).is_some() {
+                    self.search_history_index = None;
+                    self.search_input_changed();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Re-runs the search after the query being typed changes.
+    fn search_input_changed(&mut self) {
+        if !self.search_case_overridden {
+            // Smart-case: ignore case unless the query contains an uppercase letter
+            self.search_case_insensitive = !self.input.value().chars().any(char::is_uppercase);
+        }
+        self.update_search();
+    }
+
+    /// Replaces the query being typed with the previous one in [`App::search_history`], saving
+    /// what was typed (to come back to with [`App::recall_newer_search`]) if it wasn't recalled
+    /// itself.
+    fn recall_older_search(&mut self) {
+        let history_index = match self.search_history_index {
+            _ if self.search_history.is_empty() => return,
+            None => {
+                self.search_draft = self.input.value().to_owned();
+                self.search_history.len() - 1
+            }
+            Some(history_index) => history_index.saturating_sub(1),
+        };
+
+        self.search_history_index = Some(history_index);
+        self.input = Input::new(self.search_history[history_index].clone());
+        self.search_input_changed();
+    }
+
+    /// Replaces the query being typed with the next one in [`App::search_history`], or with what
+    /// was being typed before recalling any if there are no more.
+    fn recall_newer_search(&mut self) {
+        let Some(history_index) = self.search_history_index else {
+            return;
+        };
+
+        self.search_history_index =
+            Some(history_index + 1).filter(|&index| index < self.search_history.len());
+        self.input = Input::new(match self.search_history_index {
+            Some(history_index) => self.search_history[history_index].clone(),
+            None => mem::take(&mut self.search_draft),
+        });
+        self.search_input_changed();
+    }
+
+    /// Handles an event while the user is typing a command.
+    fn handle_command_input(&mut self, event: Event) {
+        match event {
+            Event::Key(key) if key.code == KeyCode::Enter => {
+                let command = self.input.value().trim().to_owned();
+                self.input.reset();
+                self.input_mode = InputMode::Normal;
+                self.run_command(&command);
             }
+            Event::Key(key) if key.code == KeyCode::Esc => {
+                self.input.reset();
+                self.input_mode = InputMode::Normal;
+            }
+            other_event => drop(self.input.handle_event(&other_event)),
+        }
+    }
+
+    /// Handles an event while the user is typing the section to open a page from (see
+    /// [`InputMode::Section`]).
+    fn handle_section_input<B>(&mut self, terminal: &mut Terminal<B>, event: Event) -> Result<()>
+    where
+        B: Backend,
+    {
+        match event {
+            Event::Key(key) if key.code == KeyCode::Enter => {
+                let section_number = self.input.value().trim().to_owned();
+                self.input.reset();
+                let InputMode::Section { name } = mem::take(&mut self.input_mode) else {
+                    unreachable!("only called in InputMode::Section");
+                };
+
+                match ManPageInfo::new(&name, &section_number) {
+                    Ok(info) => self.follow_link(terminal, &info)?,
+                    Err(e) => self.set_status(e.to_string()),
+                }
+            }
+            Event::Key(key) if key.code == KeyCode::Esc => {
+                self.input.reset();
+                self.input_mode = InputMode::Normal;
+            }
+            other_event => drop(self.input.handle_event(&other_event)),
+        }
+
+        Ok(())
+    }
+
+    /// Handles an event while the user is typing a keyword to find pages about (see
+    /// [`InputMode::Apropos`]), offering the pages found to open.
+    fn handle_apropos_input<B>(&mut self, terminal: &mut Terminal<B>, event: Event) -> Result<()>
+    where
+        B: Backend,
+    {
+        match event {
+            Event::Key(key) if key.code == KeyCode::Enter => {
+                let keyword = self.input.value().trim().to_owned();
+                self.input.reset();
+                self.input_mode = InputMode::Normal;
+                if keyword.is_empty() {
+                    return Ok(());
+                }
+
+                let mut pages = apropos(&keyword);
+                match pages.len() {
+                    0 => self.set_status(format!("Nothing appropriate for {keyword}")),
+                    // There's nothing to choose between
+                    1 => {
+                        let (name, section_number, _) = pages.remove(0);
+                        match ManPageInfo::new(&name, &section_number) {
+                            Ok(info) => self.follow_link(terminal, &info)?,
+                            Err(e) => self.set_status(e.to_string()),
+                        }
+                    }
+                    count => {
+                        self.page_chooser = Some(PageChooser {
+                            title: format!("{count} pages about {keyword}"),
+                            pages,
+                            selected: 0,
+                        })
+                    }
+                }
+            }
+            Event::Key(key) if key.code == KeyCode::Esc => {
+                self.input.reset();
+                self.input_mode = InputMode::Normal;
+            }
+            other_event => drop(self.input.handle_event(&other_event)),
+        }
+
+        Ok(())
+    }
+
+    /// Handles an event while asking whether to quit, returning whether the user confirmed it.
+    /// Any key other than `y` cancels.
+    fn handle_quit_confirmation(&mut self, event: Event) -> bool {
+        let Event::Key(key) = event else {
+            return false;
+        };
+
+        self.input_mode = InputMode::Normal;
+        matches!(key.code, KeyCode::Char('y' | 'Y'))
+    }
+
+    /// Enters [`InputMode::Save`], to save the page as plain text (or if `formatted` is set, with
+    /// its original ANSI formatting).
+    fn start_save(&mut self, formatted: bool) {
+        self.status_message = None;
+        self.pending_overwrite = None;
+        self.input_mode = InputMode::Save { formatted };
+    }
+
+    /// Handles an event while the user is typing the filename to save the page as.
+    fn handle_save_input(&mut self, event: Event, formatted: bool) {
+        match event {
+            Event::Key(key) if key.code == KeyCode::Enter => {
+                let filename = self.input.value().trim();
+                if filename.is_empty() {
+                    return;
+                }
+                let path = match filename.strip_prefix("~/").zip(env::var_os("HOME")) {
+                    Some((rest, home)) => PathBuf::from(home).join(rest),
+                    None => PathBuf::from(filename),
+                };
+
+                // Ask before overwriting anything, and only save once the same path is entered again
+                if path.exists() && self.pending_overwrite.as_ref() != Some(&path) {
+                    self.pending_overwrite = Some(path);
+                    return;
+                }
+
+                let contents = if formatted {
+                    self.page.content()
+                } else {
+                    self.page.plain_text()
+                };
+                self.set_status(match fs::write(&path, contents) {
+                    Ok(()) => format!("Saved the page to {}", path.display()),
+                    Err(e) => format!("Failed to save the page to {}: {e}", path.display()),
+                });
+                self.input.reset();
+                self.pending_overwrite = None;
+                self.input_mode = InputMode::Normal;
+            }
+            Event::Key(key) if key.code == KeyCode::Esc => {
+                self.input.reset();
+                self.pending_overwrite = None;
+                self.input_mode = InputMode::Normal;
+            }
+            other_event => {
+                // A different filename needs its own confirmation
+                if self
+                    .input
+                    .handle_event(&other_event)
+                    .is_some_and(|change| change.value)
+                {
+                    self.pending_overwrite = None;
+                }
+            }
+        }
+    }
+
+    /// Runs a command typed after `:`. The only command is currently a line number, which jumps to
+    /// that line.
+    fn run_command(&mut self, command: &str) {
+        if command.is_empty() {
+            return;
+        }
+
+        match command.parse::<usize>() {
+            Ok(line_number) => self.go_to_line(line_number),
+            Err(_) => self.set_status(format!("Not a line number: {command}")),
+        }
+    }
+
+    /// Records the scroll position as mark `letter`, or jumps back to it.
+    fn use_mark(&mut self, mark_command: MarkCommand, letter: char) {
+        match mark_command {
+            MarkCommand::Set => {
+                self.marks.insert(letter, self.scroll);
+                self.set_status(format!("Marked position as '{letter}'"));
+            }
+            MarkCommand::Jump => match self.marks.get(&letter) {
+                Some(&scroll) => self.jump_to(scroll),
+                None => self.set_status(format!("Mark '{letter}' not set")),
+            },
+        }
+    }
+
+    /// Scrolls so (1-based) `line_number` is at the top of the screen, clamped to the last line (or,
+    /// rather, as far as we can scroll).
+    fn go_to_line(&mut self, line_number: usize) {
+        self.jump_to(line_number.saturating_sub(1));
+    }
+
+    /// Scrolls to `scroll` (stopping at [`App::max_scroll`]) as a jump, remembering where we were
+    /// for [`App::jump_back`].
+    fn jump_to(&mut self, scroll: usize) {
+        let previous_scroll = self.scroll;
+        self.scroll = scroll.min(self.max_scroll());
+        self.record_jump_from(previous_scroll);
+    }
+
+    /// Remembers `previous_scroll` for [`App::jump_back`] if we've since jumped away from it.
+    /// Ordinary scrolling shouldn't call this, so jumping back skips over it.
+    fn record_jump_from(&mut self, previous_scroll: usize) {
+        if previous_scroll != self.scroll {
+            self.previous_position = Some(previous_scroll);
+        }
+    }
+
+    /// Returns to where we were before the most recent jump (`G`, a search, a section, etc.). Doing
+    /// so is itself a jump, so doing it twice returns to where we started.
+    fn jump_back(&mut self) {
+        match self.previous_position {
+            Some(previous_scroll) => self.jump_to(previous_scroll),
+            None => self.set_status(String::from("No previous position to jump back to")),
+        }
+    }
+
+    /// Opens the man page described by `info` (see [`try_link_jump`]), then restores our terminal
+    /// state and scroll position once it's closed.
+    ///
+    /// With `--in-process`, the page is instead swapped in place of this one, which is pushed onto
+    /// [`App::back_stack`] to go back to.
+    fn follow_link<B>(&mut self, terminal: &mut Terminal<B>, info: &ManPageInfo) -> Result<()>
+    where
+        B: Backend,
+    {
+        if self.in_process {
+            match self.page_cache.get(info) {
+                Ok(content) => {
+                    let previous = self.swap_page(VisitedPage {
+                        page_id: info.to_string(),
+                        content,
+                        ..Default::default()
+                    })?;
+                    self.back_stack.push(previous);
+                    // Like in a browser, following a link starts a new path forward
+                    self.forward_stack.clear();
+                    self.update_title();
+                }
+                Err(e) => {
+                    self.set_status(e.to_string());
+                    self.link_jump_failed = true;
+                }
+            }
+
+            return Ok(());
+        }
+
+        let scroll = self.scroll;
+
+        // The nested linkman starts with the preferences in the state file, so bring it up to date
+        self.save_state();
+
+        let jump = self.page_cache.get(info).and_then(|content| {
+            try_link_jump(
+                &content,
+                &info.to_string(),
+                &self.history(),
+                &self.jump_flags(),
+            )
+        });
+        if let Err(e) = jump {
+            // Let the user know why nothing happened
+            self.set_status(e.to_string());
+            self.link_jump_failed = true;
+        } else {
+            // The nested linkman tore down the terminal state we share with it when it exited, so set it back up
+            terminal_guard::reacquire()?;
+
+            // There's no need to re-apply the program mouse mode unless man ran successfully (and therefore [probably] ran us again)
+
+            self.apply_mouse_mode()?;
+        }
+
+        // Land back where we were before the jump
+        self.scroll = scroll;
+
+        // Clear terminal even if try_link_jump failed, since the nested linkman may have drawn over
+        // (or, if it failed, printed an error over) our screen
+        terminal.clear()?;
+
+        Ok(())
+    }
+
+    /// Returns the first of `man_refs` (see [`text_handling::man_refs_at_position`]) that there's a
+    /// page for, or else the last of them, so following it shows why it couldn't be opened.
+    fn choose_man_ref<'a>(&mut self, man_refs: &'a [String]) -> Option<ManPageInfo<'a>> {
+        let mut infos: Vec<_> = man_refs
+            .iter()
+            .filter_map(|man_ref| ManPageInfo::try_from(man_ref.as_str()).ok())
+            .collect();
+        let last = infos.pop()?;
+
+        // The page is cached for `follow_link` once found
+        Some(
+            infos
+                .into_iter()
+                .find(|info| self.page_cache.get(info).is_ok())
+                .unwrap_or(last),
+        )
+    }
+
+    /// Finds the man reference under the mouse pointer (if any) for [`App::render`] to highlight.
+    fn update_hovered_link(&mut self) {
+        self.hovered_link = None;
+
+        // Without mouse capture, the last known pointer position is stale
+        if matches!(self.mouse_mode, MouseMode::TextSelection) {
+            return;
+        }
+        let Some((column, row)) = self.mouse_position else {
+            return;
+        };
+        if !(1..=self.content_height()).contains(&(row as usize)) {
+            return;
+        }
+
+        let Some(word) = text_handling::word_span_at_position(
+            self.page.lines(),
+            self.scroll,
+            row as usize,
+            column as usize + self.hscroll as usize,
+        ) else {
+            return;
+        };
+
+        // Only what's underlined as a link is highlighted as one
+        self.hovered_link = self
+            .page
+            .man_refs(word.line_index)
+            .iter()
+            .find(|range| range.contains(&word.bytes.start))
+            .map(|range| (word.line_index, range.clone()));
+    }
+
+    /// Opens the page named `name` (with no section given), first asking which section to open it
+    /// from if there's more than one page with that name.
+    fn open_bare_name<B>(&mut self, terminal: &mut Terminal<B>, name: String) -> Result<()>
+    where
+        B: Backend,
+    {
+        let sections = whatis(&name);
+        if sections.len() > 1 {
+            self.page_chooser = Some(PageChooser {
+                title: String::from("Open which section?"),
+                pages: sections
+                    .into_iter()
+                    .map(|(section_number, description)| {
+                        (name.clone(), section_number, description)
+                    })
+                    .collect(),
+                selected: 0,
+            });
+            return Ok(());
+        }
+
+        // There's at most one page by this name, so let man find it (or fail to)
+        match ManPageInfo::new(&name, "") {
+            Ok(info) => self.follow_link(terminal, &info),
+            Err(e) => {
+                self.set_status(e.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    /// Handles a key pressed while the [`PageChooser`] popup is open.
+    fn handle_page_chooser_key<B>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        key: KeyEvent,
+    ) -> Result<()>
+    where
+        B: Backend,
+    {
+        let Some(chooser) = &mut self.page_chooser else {
+            return Ok(());
+        };
+
+        match (self.config.keys.action(&key), key.code) {
+            (Some(Action::ScrollDown), _) | (_, KeyCode::Down) => {
+                chooser.selected = (chooser.selected + 1).min(chooser.pages.len() - 1);
+            }
+            (Some(Action::ScrollUp), _) | (_, KeyCode::Up) => {
+                chooser.selected = chooser.selected.saturating_sub(1);
+            }
+            (_, KeyCode::Enter) => {
+                let chooser = self.page_chooser.take().expect("checked above");
+                let (name, section_number, _) = &chooser.pages[chooser.selected];
+                match ManPageInfo::new(name, section_number) {
+                    Ok(info) => self.follow_link(terminal, &info)?,
+                    Err(e) => self.set_status(e.to_string()),
+                }
+            }
+            (Some(Action::Quit), _) | (_, KeyCode::Esc) => self.page_chooser = None,
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Shows the page (with its original formatting) in `$PAGER` (or `less -R`), handing the
+    /// terminal over to it until it exits.
+    fn open_in_pager<B>(&mut self, terminal: &mut Terminal<B>) -> Result<()>
+    where
+        B: Backend,
+    {
+        let pager = env::var("PAGER")
+            .ok()
+            .filter(|pager| !pager.trim().is_empty())
+            .unwrap_or_else(|| String::from(DEFAULT_PAGER));
+
+        terminal_guard::suspend()?;
+        // `$PAGER` may include arguments, so leave splitting it up to the shell
+        let result = Command::new("sh")
+            .args(["-c", &pager])
+            .stdin(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                // A failed write means the pager quit before reading everything, which is fine
+                if let Some(mut stdin) = child.stdin.take() {
+                    drop(stdin.write_all(self.page.content().as_bytes()));
+                }
+                child.wait()
+            });
+        terminal_guard::reacquire()?;
+        self.apply_mouse_mode()?;
+        terminal.clear()?;
+
+        match result {
+            Ok(status) if !status.success() => {
+                self.set_status(format!("{pager} exited with {status}"))
+            }
+            Ok(_) => (),
+            Err(e) => self.set_status(format!("Failed to run {pager}: {e}")),
+        }
+
+        Ok(())
+    }
+
+    /// Opens the source (e.g. roff or mdoc) of the page in `$VISUAL` or `$EDITOR` (or `vi`), handing
+    /// the terminal over to it until it exits, then reloads the page to show any changes made.
+    fn edit_source<B>(&mut self, terminal: &mut Terminal<B>) -> Result<()>
+    where
+        B: Backend,
+    {
+        let page_id = self.page_id.clone();
+        let Ok(info) = ManPageInfo::try_from(page_id.as_str()) else {
+            self.set_status(format!(
+                "{page_id} isn't a man page, so it has no source to edit"
+            ));
+            return Ok(());
+        };
+        let path = match man_source_path(&info) {
+            Ok(path) => path,
+            Err(e) => {
+                self.set_status(e.to_string());
+                return Ok(());
+            }
+        };
+
+        let editor = ["VISUAL", "EDITOR"]
+            .into_iter()
+            .filter_map(|variable| env::var(variable).ok())
+            .find(|editor| !editor.trim().is_empty())
+            .unwrap_or_else(|| String::from(DEFAULT_EDITOR));
+
+        terminal_guard::suspend()?;
+        // Like `$PAGER`, `$EDITOR` may include arguments
+        let result = Command::new("sh")
+            .args(["-c", &format!("{editor} {}", shell_quote(&path))])
+            .status();
+        terminal_guard::reacquire()?;
+        self.apply_mouse_mode()?;
+        terminal.clear()?;
+
+        match result {
+            Ok(status) if !status.success() => {
+                self.set_status(format!("{editor} exited with {status}"))
+            }
+            Ok(_) => self.reload()?,
+            Err(e) => self.set_status(format!("Failed to run {editor}: {e}")),
+        }
+
+        Ok(())
+    }
+
+    /// Opens `url` (a web or `mailto:` address) with the system's default handler, reporting
+    /// failures on the status line.
+    fn open_external(&mut self, url: &str) {
+        let status = Command::new(URL_OPENER)
+            .arg(url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+
+        self.set_status(match status {
+            Ok(status) if status.success() => format!("Opened {url}"),
+            Ok(_) => format!("{URL_OPENER} failed to open {url}"),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                format!("Can't open {url}: {URL_OPENER} is not installed")
+            }
+            Err(e) => format!("Can't open {url}: {e}"),
+        });
+    }
+
+    /// Copies the man reference selected with Tab/Shift+Tab (or, if none is, the one under the
+    /// mouse pointer) to the clipboard.
+    fn copy_link(&mut self) {
+        let selected_word = match &self.selected_link {
+            Some((line_index, range)) => self.page.lines()[*line_index].get(range.clone()),
+            None => self.mouse_position.and_then(|(column, row)| {
+                text_handling::word_at_position(
+                    self.page.lines(),
+                    self.scroll,
+                    row as usize,
+                    column as usize + self.hscroll as usize,
+                )
+            }),
+        };
+
+        let Some(info) = selected_word.and_then(|word| ManPageInfo::try_from(word).ok()) else {
+            self.set_status(String::from("No link selected or under the mouse"));
+            return;
+        };
+
+        let reference = info.to_string();
+        let message = match self.clipboard.copy(reference.as_str()) {
+            Ok(()) => format!("Copied {reference} to the clipboard"),
+            Err(e) => format!("Couldn't copy to the clipboard: {e}"),
+        };
+        self.set_status(message);
+    }
+
+    /// Runs `man` again to show the current page as it is now (e.g. after it's been updated or
+    /// edited), staying at the same position in it.
+    fn reload(&mut self) -> Result<()> {
+        let page_id = self.page_id.clone();
+        let Ok(info) = ManPageInfo::try_from(page_id.as_str()) else {
+            self.set_status(format!(
+                "{page_id} isn't a man page, so it can't be reloaded"
+            ));
+            return Ok(());
+        };
+
+        match self.page_cache.reload(&info) {
+            Ok(content) => {
+                self.page.replace_content(content)?;
+                self.page_laid_out();
+                self.fit_wrap()?;
+                self.scroll = self.scroll.min(self.max_scroll());
+                self.hscroll = self.hscroll.min(self.max_hscroll());
+                self.set_status(format!("Reloaded {info}"));
+            }
+            Err(e) => self.set_status(e.to_string()),
+        }
+
+        Ok(())
+    }
+
+    /// Copies the `man` command that opens the current page (e.g. `man 2 open`) to the clipboard.
+    fn copy_man_command(&mut self) {
+        let Ok(info) = ManPageInfo::try_from(self.page_id.as_str()) else {
+            self.set_status(format!("{} isn't a man page", self.page_id));
+            return;
+        };
+
+        let command = info.man_command();
+        let message = match self.clipboard.copy(command.as_str()) {
+            Ok(()) => format!("Copied `{command}` to the clipboard"),
+            Err(e) => format!("Couldn't copy to the clipboard: {e}"),
+        };
+        self.set_status(message);
+    }
+
+    /// Copies the (ANSI-stripped) text of the whole page, or of just the lines on screen if
+    /// `visible_only` is set, to the clipboard.
+    fn copy_text(&mut self, visible_only: bool) {
+        let text = if visible_only {
+            self.page.lines()[self.visible_lines()].join("\n")
+        } else {
+            self.page.plain_text().to_owned()
+        };
+        let (num_bytes, num_lines) = (text.len(), text.lines().count());
+
+        let message = match self.clipboard.copy(text) {
+            Ok(()) => format!("Copied {num_lines} lines ({num_bytes} bytes) to the clipboard"),
+            Err(e) => format!("Couldn't copy to the clipboard: {e}"),
+        };
+        self.set_status(message);
+    }
+
+    /// Moves the keyboard link selection to the next (or, if `forward` is unset, the previous) man
+    /// reference on screen, wrapping around at either end of the screen.
+    fn select_link(&mut self, forward: bool) {
+        let links = self.visible_links();
+
+        if links.is_empty() {
+            self.selected_link = None;
+            self.set_status(String::from("No links on screen"));
+            return;
+        }
+
+        let current = self
+            .selected_link
+            .as_ref()
+            .and_then(|selected| links.iter().position(|link| link == selected));
+        let link_index = match (current, forward) {
+            (Some(index), true) => (index + 1) % links.len(),
+            (Some(index), false) => (index + links.len() - 1) % links.len(),
+            (None, true) => 0,
+            (None, false) => links.len() - 1,
+        };
+
+        self.selected_link = links.into_iter().nth(link_index);
+    }
+
+    /// Labels the links on screen (see [`LinkHints`]), so one can be followed by typing its label.
+    fn show_link_hints(&mut self) {
+        let links = self.visible_links();
+        if links.is_empty() {
+            self.set_status(String::from("No links on screen"));
+        } else {
+            self.status_message = None;
+            self.link_hints = Some(LinkHints {
+                links,
+                typed: String::new(),
+            });
+        }
+    }
+
+    /// Handles a key typed while links are labeled: part of a label, which follows its link once
+    /// it's complete. Esc, or anything that isn't part of a label, stops labeling links.
+    fn handle_link_hint_key<B>(&mut self, terminal: &mut Terminal<B>, key: KeyEvent) -> Result<()>
+    where
+        B: Backend,
+    {
+        let Some(mut hints) = self.link_hints.take() else {
+            return Ok(());
+        };
+        let KeyCode::Char(c) = key.code else {
+            return Ok(());
+        };
+        hints.typed.push(c);
+
+        let matching: Vec<usize> = hints.matching().collect();
+        match matching.as_slice() {
+            [] => self.set_status(format!("No link labeled '{}'", hints.typed)),
+            &[index] if hints.label(index) == hints.typed => {
+                let (line_index, range) = &hints.links[index];
+                let word = self.page.lines()[*line_index][range.clone()].to_owned();
+                if let Ok(info) = ManPageInfo::try_from(word.as_str()) {
+                    self.follow_link(terminal, &info)?;
+                }
+            }
+            _ => self.link_hints = Some(hints),
+        }
+
+        Ok(())
+    }
+
+    /// Draws the labels of `hints` (the part of them not typed yet) over the start of their links,
+    /// in `area` (where the page is drawn, borders included).
+    fn render_link_hints(&self, frame: &mut Frame, hints: &LinkHints, area: Rect) {
+        let inner = area.inner(Margin {
+            vertical: 1,
+            horizontal: 1,
+        });
+        for index in hints.matching() {
+            let (line_index, range) = &hints.links[index];
+            let Some(row) = line_index
+                .checked_sub(self.scroll)
+                .and_then(|row| u16::try_from(row).ok())
+                .filter(|&row| row < inner.height)
+            else {
+                continue;
+            };
+            let Some(column) = self.page.lines()[*line_index][..range.start]
+                .width()
+                .checked_sub(self.hscroll as usize)
+                .and_then(|column| u16::try_from(column).ok())
+                .filter(|&column| column < inner.width)
+            else {
+                continue;
+            };
+
+            let label = hints.label(index);
+            let label = &label[hints.typed.len()..];
+            let label_area = Rect {
+                x: inner.x + column,
+                y: inner.y + row,
+                width: (label.len() as u16).min(inner.width - column),
+                height: 1,
+            };
+            let style = if self.no_color {
+                Style::new().reversed().bold()
+            } else {
+                Style::new().black().bg(self.config.colors.link_hint).bold()
+            };
+            frame.render_widget(Paragraph::new(label).style(style), label_area);
+        }
+    }
+
+    /// Returns the line index and byte range of every man reference on screen, in order (see
+    /// [`Page::man_refs`]).
+    fn visible_links(&self) -> Vec<(usize, Range<usize>)> {
+        self.visible_lines()
+            .flat_map(|line_index| {
+                self.page
+                    .man_refs(line_index)
+                    .iter()
+                    .map(move |range| (line_index, range.clone()))
+            })
+            .collect()
+    }
+
+    /// Returns the range of indices into [`App::page`]'s lines that are currently on screen.
+    fn visible_lines(&self) -> Range<usize> {
+        let start = self.scroll.min(self.page.lines().len());
+        let end = (start + self.content_height()).min(self.page.lines().len());
+
+        start..end
+    }
+
+    /// Toggles the [`App::mouse_mode`] (between [`MouseMode::LinkClicking`] and
+    /// [`MouseMode::TextSelection`].
+    fn toggle_mouse_mode(&mut self) -> Result<()> {
+        let mut stdout = io::stdout();
+
+        if matches!(self.mouse_mode, MouseMode::LinkClicking) {
+            // Allow text selection by disabling mouse capture
+            execute!(stdout, DisableMouseCapture)?;
+
+            // Update program state
+            self.mouse_mode = MouseMode::TextSelection;
+        } else {
+            // Allow link-clicking by enabling mouse capture
+            execute!(stdout, EnableMouseCapture)?;
+
+            // Update program state
+            self.mouse_mode = MouseMode::LinkClicking;
+        }
+        self.state.mouse_capture = matches!(self.mouse_mode, MouseMode::LinkClicking);
+
+        Ok(())
+    }
+
+    /// Toggles showing the page in color, or with only bold, underline, and reverse video.
+    fn toggle_colors(&mut self) -> Result<()> {
+        self.no_color = !self.no_color;
+        self.state.color = !self.no_color;
+        self.page.set_no_color(self.no_color)?;
+        self.set_status(String::from(if self.no_color {
+            "Colors off"
+        } else {
+            "Colors on"
+        }));
+
+        Ok(())
+    }
+
+    /// Applies the current [`App::mouse_mode`] by enabling or disabling mouse capture.
+    /// This is used to verify we are correctly handling user clicks after a man command successfully runs.
+    fn apply_mouse_mode(&self) -> Result<()> {
+        let mut stdout = io::stdout();
+
+        match self.mouse_mode {
+            MouseMode::LinkClicking => execute!(stdout, EnableMouseCapture)?,
+            MouseMode::TextSelection => execute!(stdout, DisableMouseCapture)?,
+        }
+
+        Ok(())
+    }
+
+    /// Enters [`InputMode::Search`], remembering the scroll position so that
+    /// [`App::cancel_search`] can restore it.
+    fn start_search(&mut self) {
+        self.status_message = None;
+        // Smart-case (see `search_input_changed`), unless case was last chosen explicitly
+        self.search_case_insensitive = self.state.search_ignore_case.unwrap_or(true);
+        self.search_case_overridden = self.state.search_ignore_case.is_some();
+        self.search_section = None;
+        self.pre_search_scroll = self.scroll;
+        self.search_history_index = None;
+        self.input_mode = InputMode::Search;
+    }
+
+    /// Abandons the query being typed, dropping its matches and restoring the pre-search scroll
+    /// position.
+    fn cancel_search(&mut self) {
+        self.input_mode = InputMode::Normal;
+        self.clear_search();
+        self.scroll = self.pre_search_scroll;
+    }
+
+    /// Drops the current search query, its matches, and the selected match.
+    fn clear_search(&mut self) {
+        self.input.reset();
+        self.search_query.clear();
+        self.search_section = None;
+        self.search_matches.clear();
+        self.current_match = None;
+        self.current_match_position = None;
+    }
+
+    /// Commits the query being typed, keeping its matches highlighted.
+    fn perform_search(&mut self) -> Result<()> {
+        self.input.reset();
+        self.input_mode = InputMode::Normal;
+
+        // The search's jump happened as the query was typed, so it started from before the search
+        self.record_jump_from(self.pre_search_scroll);
+
+        // Remember an explicit choice of case for later searches, unless it's what smart-case would
+        // have chosen anyway (which is how to go back to smart-case)
+        let smart_case_insensitive = !self.search_query.chars().any(char::is_uppercase);
+        self.state.search_ignore_case = (self.search_case_overridden
+            && self.search_case_insensitive != smart_case_insensitive)
+            .then_some(self.search_case_insensitive);
+
+        // Remember the query (only once, as the most recent) for recalling with Up
+        if !self.search_query.is_empty() {
+            self.search_history
+                .retain(|query| *query != self.search_query);
+            self.search_history.push(self.search_query.clone());
+        }
+
+        if !self.search_query.is_empty() && self.search_matches.is_empty() {
+            self.set_status(format!("Pattern not found: {}", self.search_query));
+        }
+
+        Ok(())
+    }
+
+    /// Searches [`App::page`]'s lines for the query typed so far, recording every match in
+    /// [`App::search_matches`] and scrolling to the first match at or below the pre-search scroll
+    /// position (wrapping around to the first match in the page if there is none below).
+    fn update_search(&mut self) {
+        self.search_query = self.input.value().to_owned();
+        self.search_matches = self.find_search_matches();
+        self.current_match = None;
+        self.current_match_position = None;
+
+        match self
+            .search_matches
+            .iter()
+            .position(|&(line_index, _)| line_index >= self.pre_search_scroll)
+            .or_else(|| (!self.search_matches.is_empty()).then_some(0))
+        {
+            Some(match_index) => self.jump_to_match(match_index),
+            None => self.scroll = self.pre_search_scroll,
+        }
+    }
+
+    /// Returns the matches for [`App::search_query`] in [`App::page`]'s lines, leaving out any
+    /// outside [`App::search_section`] if the search is limited to one.
+    fn find_search_matches(&self) -> Vec<(usize, Range<usize>)> {
+        let mut matches = text_handling::find_matches(
+            self.page.lines(),
+            self.page.line_starts(),
+            &self.search_query,
+            self.search_case_insensitive,
+        );
+        if let Some(section) = &self.search_section {
+            matches.retain(|(line_index, _)| section.contains(line_index));
+        }
+
+        matches
+    }
+
+    /// Returns the range of lines in the SYNOPSIS section (after its heading), which is empty if
+    /// there isn't one.
+    fn find_synopsis(&self) -> Range<usize> {
+        self.headings
+            .iter()
+            .find(|&&line_index| self.page.lines()[line_index].trim() == "SYNOPSIS")
+            .map_or(0..0, |&heading| {
+                let section = self.section_around(heading);
+                (heading + 1)..section.end
+            })
+    }
+
+    /// Returns the range of lines in the section (from one heading up to the next) containing line
+    /// `line_index`. Lines before the first heading count as a section of their own.
+    fn section_around(&self, line_index: usize) -> Range<usize> {
+        let start = self
+            .headings
+            .iter()
+            .rfind(|&&heading| heading <= line_index)
+            .copied()
+            .unwrap_or(0);
+        let end = self
+            .headings
+            .iter()
+            .find(|&&heading| heading > line_index)
+            .copied()
+            .unwrap_or(self.page.lines().len());
+
+        start..end
+    }
+
+    /// Selects the first search match after the current one (or after the top of the screen, if
+    /// no match is selected), wrapping around to the first match in the page.
+    fn next_match(&mut self) {
+        let position = self.current_position();
+        let match_index = self
+            .search_matches
+            .iter()
+            .position(|&(line_index, Range { start, .. })| (line_index, start) > position)
+            .unwrap_or(0);
+
+        let previous_scroll = self.scroll;
+        self.jump_to_match(match_index);
+        self.record_jump_from(previous_scroll);
+    }
+
+    /// Selects the last search match before the current one (or before the top of the screen, if
+    /// no match is selected), wrapping around to the last match in the page.
+    fn previous_match(&mut self) {
+        let position = self.current_position();
+        let match_index = self
+            .search_matches
+            .iter()
+            .rposition(|&(line_index, Range { start, .. })| (line_index, start) < position)
+            .unwrap_or(self.search_matches.len().saturating_sub(1));
+
+        let previous_scroll = self.scroll;
+        self.jump_to_match(match_index);
+        self.record_jump_from(previous_scroll);
+    }
+
+    /// Returns the `(line index, byte offset)` that `n`/`N` should search relative to.
+    fn current_position(&self) -> (usize, usize) {
+        self.current_match
+            .and_then(|match_index| self.search_matches.get(match_index))
+            .map_or((self.scroll, 0), |&(line_index, Range { start, .. })| {
+                (line_index, start)
+            })
+    }
+
+    /// Selects the search match at `match_index` and scrolls it to the top of the screen (as far as
+    /// the scroll bounds allow). Does nothing if there is no such match.
+    fn jump_to_match(&mut self, match_index: usize) {
+        if let Some(&(line_index, Range { start, .. })) = self.search_matches.get(match_index) {
+            self.current_match = Some(match_index);
+            self.current_match_position = Some(self.unwrapped_position((line_index, start)));
+            self.scroll = line_index.min(self.max_scroll());
+        }
+    }
+
+    /// Returns the width of the table of contents sidebar: enough for the widest heading (in
+    /// columns, so wide characters count double), plus its borders and some padding.
+    fn toc_width(&self) -> u16 {
+        let widest_heading = self
+            .headings
+            .iter()
+            .map(|&line_index| self.page.lines()[line_index].trim().width())
+            .max()
+            .unwrap_or_default();
+
+        u16::try_from(widest_heading)
+            .unwrap_or(u16::MAX)
+            .max(8)
+            .saturating_add(4)
+    }
+
+    /// Returns the width of the line number gutter (when it's shown): room for the largest line
+    /// number, and a space to set it apart from the page.
+    fn gutter_width(&self) -> u16 {
+        self.page.lines().len().max(1).ilog10() as u16 + 2
+    }
+
+    /// Opens or closes the table of contents sidebar. When opening, the heading of the section at
+    /// the top of the screen is selected.
+    fn toggle_toc(&mut self) -> Result<()> {
+        self.show_toc = !self.show_toc;
+        if self.show_toc {
+            self.toc_selected = self
+                .headings
+                .iter()
+                .rposition(|&line_index| line_index <= self.scroll)
+                .unwrap_or(0);
+        }
+
+        // The sidebar takes its width from (or gives it back to) the page
+        self.fit_wrap()
+    }
+
+    /// Draws the table of contents sidebar (a list of the section headings) in `area`.
+    fn render_toc(&self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .headings
+            .iter()
+            .map(|&line_index| ListItem::new(self.page.lines()[line_index].trim()))
+            .collect();
+        let mut list_state = ListState::default().with_selected(Some(self.toc_selected));
+
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title("Contents")
+                        .title_alignment(Alignment::Center),
+                )
+                .highlight_style(Style::new().reversed()),
+            area,
+            &mut list_state,
+        );
+    }
+
+    /// Draws the number of each visible line (or with [`App::relative_line_numbers`], its distance
+    /// from the top of the screen) in `area`, alongside the content's lines.
+    fn render_line_numbers(&self, frame: &mut Frame, area: Rect) {
+        let numbers: Vec<Line> = self
+            .visible_lines()
+            .map(|line_index| {
+                let number = if self.relative_line_numbers {
+                    line_index - self.scroll
+                } else {
+                    line_index + 1
+                };
+                Line::from(format!("{number} "))
+            })
+            .collect();
+
+        // Skip the row the content's top border is on
+        frame.render_widget(
+            Paragraph::new(numbers).alignment(Alignment::Right).dim(),
+            area.inner(Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+        );
+    }
+
+    /// Returns the line of the section heading named by the word at the given position (like
+    /// `OPTIONS` in "see the OPTIONS section"), if it names one. Multi-word headings (e.g.
+    /// `SEE ALSO`) have to be written out in full.
+    fn heading_named_at(&mut self, row: usize, column: usize) -> Option<usize> {
+        let LineSpan {
+            text,
+            line_index,
+            bytes,
+        } = text_handling::word_span_at_position(self.page.lines(), self.scroll, row, column)?;
+        // Clicking a heading itself goes nowhere
+        if self.headings.contains(&line_index) {
+            return None;
+        }
+
+        // Headings are quoted or followed by punctuation as often as references are
+        let word = text.trim_start_matches(|c: char| !c.is_alphanumeric());
+        let start = bytes.start + (text.len() - word.len());
+        let word = word.trim_end_matches(|c: char| !c.is_alphanumeric());
+        let end = start + word.len();
+        if !word.chars().any(char::is_alphabetic) || word.chars().any(char::is_lowercase) {
+            return None;
+        }
+
+        let line = &self.page.lines()[line_index];
+        self.headings.iter().copied().find(|&heading_index| {
+            let heading = self.page.lines()[heading_index].trim();
+            heading
+                .split_whitespace()
+                .any(|heading_word| heading_word == word)
+                && line.match_indices(heading).any(|(heading_start, _)| {
+                    heading_start <= start && end <= heading_start + heading.len()
+                })
+        })
+    }
+
+    /// Scrolls to the first section heading below the top of the screen.
+    fn next_heading(&mut self) {
+        if let Some(&line_index) = self
+            .headings
+            .iter()
+            .find(|&&line_index| line_index > self.scroll)
+        {
+            self.jump_to(line_index);
+        }
+    }
+
+    /// Scrolls to the last section heading above the top of the screen.
+    fn previous_heading(&mut self) {
+        if let Some(&line_index) = self
+            .headings
+            .iter()
+            .rfind(|&&line_index| line_index < self.scroll)
+        {
+            self.jump_to(line_index);
+        }
+    }
+
+    /// Returns a `less`-style indicator of how far through the page the bottom of the screen is,
+    /// along with the line number at the top of the screen, e.g. `45%  line 312/700`.
+    fn position_indicator(&self) -> String {
+        let num_lines = self.page.lines().len().max(1);
+        let bottom_line = (self.scroll + self.content_height()).min(num_lines);
+        let percent = bottom_line * 100 / num_lines;
+
+        format!(
+            "{percent}%  line {}/{}",
+            self.scroll.saturating_add(1).min(self.page.lines().len()),
+            self.page.lines().len()
+        )
+    }
 
-            return Ok(true);
+    /// Returns which of the search matches is selected, e.g. `[3/17]`, if there's a search.
+    fn match_indicator(&self) -> Option<String> {
+        if self.search_query.is_empty() {
+            return None;
         }
 
-        match event::read()? {
-            Event::Key(key) => match (key.code, key.modifiers) {
-                (KeyCode::Char('q'), _) => return Ok(false),
-                (KeyCode::Down, _) | (KeyCode::Char('j'), _) => self.scroll += 1,
-                (KeyCode::Up, _) | (KeyCode::Char('k'), _) => {
-                    self.scroll = self.scroll.saturating_sub(1);
-                }
-                (KeyCode::Char('G'), _) | (KeyCode::Char('g'), KeyModifiers::SHIFT) => {
-                    self.scroll = self.num_lines - self.height + 2
-                }
-                (KeyCode::Char('g'), _) => self.scroll = 0,
-                (KeyCode::Char('i'), KeyModifiers::ALT) => self.toggle_mouse_mode()?,
-                (KeyCode::Char('/'), _) => self.search_mode = SearchMode::TypingQuery,
-                _ => (),
-            },
-            Event::Mouse(mouse_event)
-                if matches!(mouse_event.kind, MouseEventKind::Up(MouseButton::Left))
-                    && (1..=self.height - 3).contains(&mouse_event.row) =>
-            {
-                // SAFETY: Calling `word_at_position` from the same single thread every time is safe
-                if let Some(word_clicked) = unsafe {
-                    text_handling::word_at_position(
-                        &self.lines,
-                        self.scroll as usize,
-                        mouse_event.row as usize,
-                        mouse_event.column as usize,
-                    )
-                } {
-                    // Ignoring failures (user probably just clicked on something that wasn't a link)
-                    if let Ok(info) = <&str as TryInto<ManPageInfo>>::try_into(word_clicked) {
-                        if try_link_jump(&info).is_ok() {
-                            // There's no need to re-apply the program mouse mode unless man ran successfully (and therefore [probably] ran us again)
-
-                            self.apply_mouse_mode()?;
-                        }
+        Some(match (self.current_match, self.search_matches.len()) {
+            (_, 0) => String::from("[no matches]"),
+            (Some(match_index), num_matches) => format!("[{}/{num_matches}]", match_index + 1),
+            (None, num_matches) => format!("[{num_matches} matches]"),
+        })
+    }
 
-                        // Clear terminal even if try_link_jump failed, since man will print a failure message we'll need to draw over if the man page doesn't exist
-                        terminal.clear()?;
-                    }
-                }
-            }
-            Event::Mouse(mouse_event) if mouse_event.kind == MouseEventKind::ScrollDown => {
-                self.scroll += 1;
-            }
-            Event::Mouse(mouse_event) if mouse_event.kind == MouseEventKind::ScrollUp => {
-                self.scroll = self.scroll.saturating_sub(1);
-            }
-            Event::Resize(cols, _) => {
-                // Terminal resize event => recalculate needed variables
-                // TODO: Evaluate how badly you need *THIS* textwrap::wrap call as well. I'm thinking you'll likely need this one a bit more than the last (already removed) one.
-                self.lines = textwrap::wrap(strip_str(&self.content).as_str(), cols as usize)
-                    .into_iter()
-                    .map(|cow| cow.into_owned())
-                    .collect();
+    /// Scrolls down by `lines`, stopping at [`App::max_scroll`].
+    fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_add(lines).min(self.max_scroll());
+    }
 
-                self.processed_content = self.lines.join("\n");
-                self.num_lines = self.lines.len() as u16; // saturating cast is desired here
+    /// Scrolls up by `lines`, stopping at the top of the page.
+    fn scroll_up(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
 
-                // SAFETY: This program has no "threads" in the sense that no two Linux tasks will ever share the same virtual memory space,
-                // so this is safe.
-                unsafe { set_man_width_variable() }?;
-            }
-            _ => (),
-        }
+    /// Toggles soft-wrapping lines to the terminal's width.
+    fn toggle_wrap(&mut self) -> Result<()> {
+        self.set_wrap(!self.wrap_enabled)?;
+        self.state.wrap = self.wrap_enabled;
+        self.set_status(String::from(if self.wrap_enabled {
+            "Line wrapping on"
+        } else {
+            "Line wrapping off (scroll sideways to read long lines)"
+        }));
 
-        Ok(true)
+        Ok(())
     }
 
-    /// Toggles the [`App::mouse_mode`] (between [`MouseMode::LinkClicking`] and
-    /// [`MouseMode::TextSelection`].
-    fn toggle_mouse_mode(&mut self) -> Result<()> {
-        let mut stdout = io::stdout();
+    /// Turns soft-wrapping lines to the terminal's width on or off, showing the page's original
+    /// layout when it's off.
+    fn set_wrap(&mut self, enabled: bool) -> Result<()> {
+        self.wrap_enabled = enabled;
+        if enabled {
+            self.hscroll = 0;
+            self.fit_wrap()?;
+        } else {
+            self.page.rewrap(None)?;
+            self.page_laid_out();
+        }
+        self.scroll = self.scroll.min(self.max_scroll());
 
-        if matches!(self.mouse_mode, MouseMode::LinkClicking) {
-            // Allow text selection by disabling mouse capture
-            execute!(stdout, DisableMouseCapture)?;
+        Ok(())
+    }
 
-            // Update program state
-            self.mouse_mode = MouseMode::TextSelection;
-        } else {
-            // Allow link-clicking by enabling mouse capture
-            execute!(stdout, EnableMouseCapture)?;
+    /// Returns the width lines are wrapped to: the inside of the page's borders, beside the table of
+    /// contents and line numbers if they're shown.
+    fn wrap_width(&self) -> usize {
+        let mut width = self.terminal_size.0;
+        if self.show_toc {
+            width = width.saturating_sub(self.toc_width());
+        }
+        if self.show_line_numbers {
+            width = width.saturating_sub(self.gutter_width());
+        }
 
-            // Update program state
-            self.mouse_mode = MouseMode::LinkClicking;
+        usize::from(width.saturating_sub(2).max(1))
+    }
+
+    /// Rewraps the page to [`App::wrap_width`], if wrapping is on and it isn't wrapped to that
+    /// already.
+    fn fit_wrap(&mut self) -> Result<()> {
+        // Rewrapping changes the number of lines, which can change the gutter's width and so the
+        // wrap width. That only ever moves the wrap width further the same way, so this settles.
+        while self.wrap_enabled && self.page.wrap_width() != Some(self.wrap_width()) {
+            self.page.rewrap(Some(self.wrap_width()))?;
+            self.page_laid_out();
         }
 
         Ok(())
     }
 
-    /// Applies the current [`App::mouse_mode`] by enabling or disabling mouse capture.
-    /// This is used to verify we are correctly handling user clicks after a man command successfully runs.
-    fn apply_mouse_mode(&self) -> Result<()> {
-        let mut stdout = io::stdout();
+    /// Scrolls left by `columns`, stopping at the start of the lines.
+    fn scroll_left(&mut self, columns: u16) {
+        self.hscroll = self.hscroll.saturating_sub(columns);
+    }
 
-        match self.mouse_mode {
-            MouseMode::LinkClicking => execute!(stdout, EnableMouseCapture)?,
-            MouseMode::TextSelection => execute!(stdout, DisableMouseCapture)?,
+    /// Scrolls right by `columns`, stopping at [`App::max_hscroll`].
+    ///
+    /// Wrapped lines all fit on screen, so this turns wrapping off to scroll through the page's
+    /// original layout instead.
+    fn scroll_right(&mut self, columns: u16) -> Result<()> {
+        if self.wrap_enabled {
+            self.set_wrap(false)?;
         }
+        self.hscroll = self.hscroll.saturating_add(columns).min(self.max_hscroll());
 
         Ok(())
     }
 
-    fn cancel_search(&mut self) {
-        self.search_input.reset();
-        self.search_mode = SearchMode::NoSearch;
+    /// Returns the largest horizontal scroll offset that still keeps the end of the longest line
+    /// on screen.
+    fn max_hscroll(&self) -> u16 {
+        self.page
+            .max_line_width()
+            .saturating_sub(self.content_width)
     }
 
-    fn perform_search(&mut self) -> Result<()> {
-        panic!(
-            "TODO: Implement search. Text for which to search was: \"{}\"",
-            self.search_input.value()
-        );
+    /// Returns the largest scroll offset that still keeps the page's content on screen.
+    ///
+    /// This is zero for pages short enough to fit on screen entirely.
+    fn max_scroll(&self) -> usize {
+        self.page
+            .lines()
+            .len()
+            .saturating_sub(self.content_height())
+    }
+
+    /// Returns how many of the page's lines fit on screen: the terminal's height, less the
+    /// content's top and bottom borders and the status line.
+    fn content_height(&self) -> usize {
+        self.height.saturating_sub(3)
     }
 }
 
+/// Help for the configurable keys, whose bindings are looked up in the [`Config`]
+const ACTION_HELP_ENTRIES: &[(Action, &str)] = &[
+    (Action::Quit, "Quit (back to the previous page if nested)"),
+    (Action::ScrollDown, "Scroll down one line"),
+    (Action::ScrollUp, "Scroll up one line"),
+    (Action::PageDown, "Scroll down one page"),
+    (Action::PageUp, "Scroll up one page"),
+    (Action::HalfPageDown, "Scroll down half a page"),
+    (Action::HalfPageUp, "Scroll up half a page"),
+    (Action::ScrollLeft, "Scroll left"),
+    (
+        Action::ScrollRight,
+        "Scroll right (showing the unwrapped page)",
+    ),
+    (
+        Action::Top,
+        "Jump to the top (pressed twice, like vim's gg, it jumps once)",
+    ),
+    (Action::Bottom, "Jump to the bottom"),
+    (
+        Action::Search,
+        "Search (Alt+C toggles case sensitivity, Alt+S limits it to this section)",
+    ),
+    (Action::Back, "Back to the previous page"),
+    (
+        Action::Forward,
+        "Forward to the page gone back from (with --in-process)",
+    ),
+];
+
+/// Keybindings (and mouse actions) that can't be configured, listed by the help overlay after
+/// [`ACTION_HELP_ENTRIES`]
+const HELP_ENTRIES: &[(&str, &str)] = &[
+    ("n / N", "Jump to the next / previous match"),
+    (
+        "Up / Down",
+        "Recall earlier / later searches while typing one",
+    ),
+    ("Esc / Ctrl+L", "Clear search highlights"),
+    ("Tab / Shift+Tab", "Select the next / previous link"),
+    ("Enter", "Follow the selected link"),
+    (
+        "f",
+        "Label the links on screen, then type a label to follow it",
+    ),
+    ("y", "Copy the selected (or hovered) link"),
+    ("Y / Alt+Y", "Copy the whole page / the lines on screen"),
+    ("c", "Copy the man command for this page (e.g. man 2 open)"),
+    ("r", "Reload the page (e.g. after editing or updating it)"),
+    ("e", "Edit the page's source in $EDITOR, then reload it"),
+    (
+        "a",
+        "Find pages about a keyword (apropos) and pick one to open",
+    ),
+    ("Alt+I", "Toggle between link clicking and text selection"),
+    ("] / [", "Jump to the next / previous section heading"),
+    ("t", "Toggle the table of contents (j/k and Enter to pick)"),
+    ("w", "Toggle line wrapping"),
+    ("C", "Toggle colors"),
+    (
+        "Ctrl+N / Alt+N",
+        "Toggle line numbers / relative line numbers",
+    ),
+    (":N", "Jump to line N"),
+    ("m<letter>", "Mark the current position"),
+    ("`<letter>", "Jump back to a marked position"),
+    ("'' / ``", "Jump back to before the last jump"),
+    (
+        "N<key>",
+        "Repeat a movement N times (NG / Ngg jump to line N)",
+    ),
+    ("|", "Open the page in $PAGER (or less -R)"),
+    ("s / S", "Save the page as plain text / with formatting"),
+    ("?", "Show this help"),
+    (
+        "Left click",
+        "Follow the clicked link or URL, or jump to the clicked section name",
+    ),
+    (
+        "Double click",
+        "Open the man page named by the clicked word",
+    ),
+    (
+        "Alt+double click",
+        "Open it from a section you type in (Enter to open)",
+    ),
+    ("Scroll wheel", "Scroll up / down"),
+];
+
+/// Draws a popup listing [`ACTION_HELP_ENTRIES`] (with the keys bound in `config`) and
+/// [`HELP_ENTRIES`] over the center of `area`.
+fn render_help(frame: &mut Frame, area: Rect, config: &Config) {
+    let entries: Vec<(String, &str)> = ACTION_HELP_ENTRIES
+        .iter()
+        .map(|&(action, description)| (config.keys.describe(action), description))
+        .chain(
+            HELP_ENTRIES
+                .iter()
+                .map(|&(keys, description)| (keys.to_owned(), description)),
+        )
+        .collect();
+
+    let key_width = entries
+        .iter()
+        .map(|(keys, _)| keys.len())
+        .max()
+        .unwrap_or_default();
+    let lines: Vec<Line> = entries
+        .iter()
+        .map(|(keys, description)| {
+            Line::from(vec![
+                Span::styled(format!(" {keys:>key_width$}  "), Style::new().bold()),
+                Span::raw(*description),
+            ])
+        })
+        .collect();
+
+    let width = lines.iter().map(Line::width).max().unwrap_or_default() as u16 + 3;
+    let popup_area = centered_rect(area, width, lines.len() as u16 + 2);
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Help (press any key to close)")
+                .title_alignment(Alignment::Center),
+        ),
+        popup_area,
+    );
+}
+
+/// Returns a `width` by `height` [`Rect`] centered in `area` (shrunk to fit in `area`, if needed).
+fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
 /// Sets the `MANWIDTH` environment variable to an appropriate width.
 ///
 /// If `MANWIDTH` is already set and parsable as a [`u16`], this function simply returns
 /// a [`std::result::Result::Ok`]. This is important since we are likely to be a child of another
 /// `linkman` process that has already set `MANWIDTH` (and already subtracted 2).
 /// Otherwise, it sets `MANWIDTH` to the number of terminal columns minus 2 (for the left and right
-/// borders).
+/// borders), taking the number of columns from `COLUMNS` if that's set and parsable as a [`u16`],
+/// or else asking the terminal.
 /// If the terminal size cannot be determined, it falls back to 78 (since `man(1)` also assumes a
 /// default width of 80).
 ///
@@ -296,8 +2567,10 @@ pub(crate) unsafe fn set_man_width_variable() -> Result<()> {
         return Ok(());
     }
 
-    let manwidth = terminal::size()
-        .map(|(cols, _)| cols)
+    let manwidth = env::var("COLUMNS")
+        .ok()
+        .and_then(|s| s.parse::<u16>().ok())
+        .or_else(|| terminal::size().map(|(cols, _)| cols).ok())
         .unwrap_or(80)
         .saturating_sub(2);
 
@@ -311,54 +2584,265 @@ pub(crate) unsafe fn set_man_width_variable() -> Result<()> {
     Ok(())
 }
 
-fn try_link_jump(info: &ManPageInfo) -> Result<()> {
-    // SAFETY:: Write this (TODO)
+/// Finds the `man` program to run, recording its path in [`MAN_PROGRAM_VARIABLE`] for
+/// [`man_program`] (and nested `linkman`s, which inherit it) to use.
+///
+/// `man_binary` (from `--man-binary`) takes precedence over a path already recorded by a parent
+/// `linkman`, which takes precedence over looking for `man` on `PATH`. A name without a `/` is
+/// looked up on `PATH`.
+///
+/// # NOTE
+/// The caller of [`find_man_program`] **must ensure** that there are no other threads
+/// concurrently reading from or writing to any environment variables.
+pub(crate) unsafe fn find_man_program(man_binary: Option<&str>) -> Result<()> {
+    let program = man_binary
+        .map(PathBuf::from)
+        .or_else(|| env::var_os(MAN_PROGRAM_VARIABLE).map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from(MAN_PROGRAM));
+
+    let path = if program.as_os_str().as_bytes().contains(&b'/') {
+        if !is_executable(&program) {
+            bail!("{} isn't an executable file", program.display());
+        }
+        program
+    } else {
+        env::var_os("PATH")
+            .iter()
+            .flat_map(env::split_paths)
+            .map(|dir| dir.join(&program))
+            .find(|path| is_executable(path))
+            .ok_or_else(|| {
+                anyhow!(
+                    "LinkMan requires the `{}` program, but it isn't on PATH (if it's installed \
+                     elsewhere, pass its path with --man-binary)",
+                    program.display()
+                )
+            })?
+    };
+
+    // SAFETY: Because the caller has upheld that no other threads are concurrently reading from or
+    // writing to any other environment variables, this is safe. See `std::env::set_var`
+    // documentation for more information.
+    unsafe {
+        env::set_var(MAN_PROGRAM_VARIABLE, path);
+    }
+
+    Ok(())
+}
+
+/// Returns the `man` program found by [`find_man_program`].
+pub(crate) fn man_program() -> PathBuf {
+    env::var_os(MAN_PROGRAM_VARIABLE).map_or_else(|| PathBuf::from(MAN_PROGRAM), PathBuf::from)
+}
+
+/// Returns whether `path` is a file that can be executed.
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+/// Returns the `(section, description)` of every page named `name`, as listed by `man -f`. Any
+/// failure to run `man` just results in no sections.
+fn whatis(name: &str) -> Vec<(String, String)> {
+    Command::new(man_program())
+        .args(["-f", name])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map(|output| text_handling::parse_whatis(&String::from_utf8_lossy(&output.stdout), name))
+        .unwrap_or_default()
+}
+
+/// Returns the `(name, section, description)` of every page whose name or description matches
+/// `keyword`, as listed by `man -k`. Any failure to run `man` (including it finding nothing, which
+/// it reports as a failure) just results in no pages.
+fn apropos(keyword: &str) -> Vec<(String, String, String)> {
+    Command::new(man_program())
+        .args(["-k", keyword])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map(|output| text_handling::parse_man_listing(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+/// Returns the path of the source file of the page described by `info`, as found by `man -w`.
+fn man_source_path(info: &ManPageInfo) -> Result<String> {
+    let args = info.as_args()?;
+    let output = Command::new(man_program())
+        .arg("-w")
+        .args(args.iter().map(|arg| OsStr::from_bytes(arg.to_bytes())))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run man to find the source of {info}"))?;
+
+    // `man -w` may list several files (e.g. for a name in several sections); the first is the one
+    // `man` would show
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .filter(|_| output.status.success())
+        .map(str::to_owned)
+        .ok_or_else(|| anyhow!("No source file found for {info}"))
+}
+
+/// Shows `content` (the formatted man page `page_ref`, see [`PageCache`]) in a nested `linkman`
+/// (see [`pager_command`]), waiting for it to exit.
+fn try_link_jump(content: &str, page_ref: &str, history: &[&str], flags: &[&str]) -> Result<()> {
+    // Everything the child needs is made before forking, since it can't allocate before it execs
+    // (see the SAFETY comment on the fork)
+    let (program, args) = pager_command(page_ref, history, flags)?;
+    let argv: Vec<*const c_char> = iter::once(program.as_ptr())
+        .chain(args.iter().map(|arg| arg.as_ptr()))
+        .chain(iter::once(ptr::null()))
+        .collect();
+
+    // The nested linkman reads the page from its stdin, like it would from man
+    let (read_fd, write_fd) = pipe()?;
+    // If the child fails to exec, it reports why (as an errno) through this pipe. Its write end is
+    // closed on exec, so a successful exec leaves the parent reading nothing from it.
+    let (error_read_fd, error_write_fd) = pipe()?;
+    // SAFETY: `error_write_fd` was just opened by `pipe` and is owned here, and this only sets its
+    // close-on-exec flag.
+    unsafe { libc::fcntl(error_write_fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+
+    // SAFETY: The child only makes async-signal-safe calls (`close`, `dup2`, `execv`, `write` and
+    // `_exit`) until it execs or exits, so it can't deadlock on a lock (e.g. the allocator's) that
+    // another thread, like the clipboard's, held when forking. Everything it passes to them was
+    // made beforehand and stays alive in the child's copy of this stack frame. If the exec fails,
+    // it leaves with `_exit`, so none of the destructors, panic hook or exit handlers of the
+    // parent's state (like restoring the terminal the parent is still using) run twice.
     let pid = unsafe { libc::fork() };
     if pid < 0 {
-        return Err(io::Error::last_os_error()).with_context(|| "libc::fork failed");
+        let error = io::Error::last_os_error();
+        for fd in [read_fd, write_fd, error_read_fd, error_write_fd] {
+            // SAFETY: There's no child to share the pipes with, so their ends are all owned here,
+            // and none of them are used again.
+            unsafe { libc::close(fd) };
+        }
+        return Err(error).with_context(|| "libc::fork failed");
     }
 
     if pid > 0 {
         // Parent
+        // SAFETY: These are the child's ends of the pipes, and the parent's copies of them are
+        // owned here and not used again.
+        unsafe {
+            libc::close(read_fd);
+            libc::close(error_write_fd);
+        }
+
+        // SAFETY: Both fds were just opened by `pipe`, and nothing else owns them. Dropping the
+        // files closes them.
+        let mut pipe = unsafe { File::from_raw_fd(write_fd) };
+        let mut error_pipe = unsafe { File::from_raw_fd(error_read_fd) };
+
+        // This returns once the child has exec'd (or failed to)
+        let mut exec_errno = Vec::new();
+        drop(error_pipe.read_to_end(&mut exec_errno));
+        drop(error_pipe);
+        let exec_error = <[u8; 4]>::try_from(exec_errno.as_slice())
+            .ok()
+            .map(|errno| io::Error::from_raw_os_error(i32::from_ne_bytes(errno)));
+
+        // Dropping the pipe lets the child see the end of the page. A failed write means the
+        // child exited early, which its exit status will explain.
+        if exec_error.is_none() {
+            drop(pipe.write_all(content.as_bytes()));
+        }
+        drop(pipe);
+
         let mut status = 0_i32;
+        // SAFETY: `status` is a valid `c_int` for `wait` to write the child's status to.
         if unsafe { libc::wait(&raw mut status) } < 0 {
             return Err(io::Error::last_os_error()).with_context(|| "libc::wait in parent failed");
         }
 
         if libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == libc::EXIT_SUCCESS {
             Ok(())
+        } else if libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == EXEC_FAILED_STATUS {
+            Err(anyhow!(
+                "Couldn't start a nested linkman: {}",
+                exec_error.map_or_else(|| String::from("unknown error"), |e| e.to_string())
+            ))
+        } else if libc::WIFEXITED(status) {
+            Err(anyhow!(
+                "Nested linkman exited with status {}",
+                libc::WEXITSTATUS(status)
+            ))
+        } else if libc::WIFSIGNALED(status) {
+            Err(anyhow!(
+                "Nested linkman was killed by signal {}",
+                libc::WTERMSIG(status)
+            ))
         } else {
             Err(anyhow!(
-                "Fork-child meant to run another man command terminated unsuccessfully"
+                "Fork-child meant to run another linkman terminated unsuccessfully"
             ))
         }
     } else {
         // Child
-        exec_self(info).inspect_err(|e| {
-            // This abnormal exit will be picked up by the parent's wait
-            panic!("{e}");
-        })
+        // SAFETY: See the SAFETY comment on the fork. The fds are the child's copies of the pipes'
+        // ends, `program` and `argv` are NUL-terminated as `execv` needs, and `error_write_fd` is
+        // still open to write to if `execv` returned (i.e. failed).
+        unsafe {
+            libc::close(write_fd);
+            libc::close(error_read_fd);
+            libc::dup2(read_fd, libc::STDIN_FILENO);
+            libc::close(read_fd);
+
+            libc::execv(program.as_ptr(), argv.as_ptr());
+
+            let errno = io::Error::last_os_error()
+                .raw_os_error()
+                .unwrap_or_default();
+            libc::write(
+                error_write_fd,
+                (&raw const errno).cast(),
+                mem::size_of_val(&errno),
+            );
+            libc::_exit(EXEC_FAILED_STATUS);
+        }
+    }
+}
+
+/// Opens a pipe, returning its `(read, write)` ends.
+fn pipe() -> Result<(i32, i32)> {
+    let mut pipe_fds = [0; 2];
+    // SAFETY: `pipe_fds` has room for the two fds `pipe` writes.
+    if unsafe { libc::pipe(pipe_fds.as_mut_ptr()) } < 0 {
+        return Err(io::Error::last_os_error()).with_context(|| "libc::pipe failed");
     }
+
+    Ok((pipe_fds[0], pipe_fds[1]))
 }
 
-pub(crate) fn exec_self(info: &ManPageInfo) -> Result<()> {
-    let canonicalized_self_program = fs::canonicalize(SELF_PROGRAM)?;
-    let pager = CString::new(format!(
-        "{} --subsequent-run",
-        canonicalized_self_program.display()
-    ))?;
-
-    let (man_section_number, man_name) = info.as_args()?;
-    let args = [
-        MAN_PROGRAM.as_ptr(),
-        c"-P".as_ptr(),
-        pager.as_ptr(),
-        man_section_number.as_ptr(),
-        man_name.as_ptr(),
-        ptr::null(),
-    ];
-
-    if unsafe { libc::execvp(MAN_PROGRAM.as_ptr(), args.as_ptr()) } < 0 {
+/// Replaces this process with `man`, using another `linkman` (run with `--subsequent-run`) as the
+/// pager to display the page described by `info`.
+///
+/// `history` holds the identifiers of the pages jumped through to reach `info`, most recent
+/// first, and is passed along so the nested `linkman` can display it. `flags` are passed along
+/// as-is (see [`Args::nested_flags`]).
+pub(crate) fn exec_self(info: &ManPageInfo, history: &[&str], flags: &[&str]) -> Result<()> {
+    let page_ref = info.to_string();
+    let mut pager = self_program()?.display().to_string();
+    for arg in pager_args(&page_ref, history, flags) {
+        pager.push(' ');
+        pager.push_str(&shell_quote(arg));
+    }
+    let pager = CString::new(pager)?;
+
+    let man_program = CString::new(man_program().into_os_string().into_vec())?;
+    let man_args = info.as_args()?;
+    let args: Vec<*const c_char> = [man_program.as_ptr(), c"-P".as_ptr(), pager.as_ptr()]
+        .into_iter()
+        .chain(man_args.iter().map(|arg| arg.as_ptr()))
+        .chain(iter::once(ptr::null()))
+        .collect();
+
+    if unsafe { libc::execvp(man_program.as_ptr(), args.as_ptr()) } < 0 {
         Err(io::Error::last_os_error()).with_context(|| "libc::execvp call failed")
     } else {
         // SAFETY: libc::execvp will not return on success: only a -1 on failure
@@ -368,11 +2852,50 @@ pub(crate) fn exec_self(info: &ManPageInfo) -> Result<()> {
     }
 }
 
+/// Returns the program and arguments to exec another `linkman` (run with `--subsequent-run`) with,
+/// which displays the page piped to its stdin just like when `man` runs it as the pager (see
+/// [`exec_self`]).
+fn pager_command(
+    page_ref: &str,
+    history: &[&str],
+    flags: &[&str],
+) -> Result<(CString, Vec<CString>)> {
+    let program = CString::new(self_program()?.into_os_string().into_vec())?;
+    let args = pager_args(page_ref, history, flags)
+        .into_iter()
+        .map(CString::new)
+        .collect::<Result<_, _>>()?;
+
+    Ok((program, args))
+}
+
+/// Returns the path of the `linkman` executable.
+fn self_program() -> Result<PathBuf> {
+    env::current_exe()
+        .or_else(|_| fs::canonicalize(SELF_PROGRAM))
+        .with_context(|| "Could not determine the path of the linkman executable")
+}
+
+/// Returns the arguments for a nested `linkman` (see [`exec_self`]).
+fn pager_args<'a>(page_ref: &'a str, history: &[&'a str], flags: &[&'a str]) -> Vec<&'a str> {
+    [SUBSEQUENT_RUN_FLAG, PAGE_REF_FLAG, page_ref]
+        .into_iter()
+        .chain(history.iter().flat_map(|&page_id| [PARENT_FLAG, page_id]))
+        .chain(flags.iter().copied())
+        .collect()
+}
+
+/// Single-quotes `s` so it is passed through as a single argument when `man` splits the pager
+/// command line.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
 /// Holds two possible program states:
 /// - `LinkClicking` may prevent text selection, but allows the user to click on, for example, `mount(2)` to open the `mount(2)` man-page.
 ///   In this mode, the program captures all mouse input.
 /// - `TextSelection` will allow text selection, but does not allow the user to click on links.
-///   They will either have to toggle the mode or use the keyboard to jump through a link (TODO).
+///   They will either have to toggle the mode or use the keyboard (Tab/Shift+Tab, then Enter) to jump through a link.
 #[derive(Copy, Clone, Debug, Default)]
 enum MouseMode {
     #[default]
@@ -380,12 +2903,423 @@ enum MouseMode {
     TextSelection,
 }
 
+/// Labels (`a`, `b`, ..., or `aa`, `ab`, ... if there are more than 26) shown over the links on
+/// screen, so one can be followed by typing its label, like browser extensions such as Vimium do
+#[derive(Debug)]
+struct LinkHints {
+    /// Line index and byte range of each labeled link, as from [`App::visible_links`]
+    links: Vec<(usize, Range<usize>)>,
+    /// The start of a label typed so far
+    typed: String,
+}
+
+impl LinkHints {
+    /// Returns the label of the link at `index` in [`LinkHints::links`].
+    fn label(&self, index: usize) -> String {
+        let letter = |n: usize| char::from(b'a' + (n % 26) as u8);
+        if self.links.len() <= 26 {
+            letter(index).to_string()
+        } else {
+            [letter(index / 26), letter(index)].iter().collect()
+        }
+    }
+
+    /// Returns the indices of the links whose labels start with what's been typed so far.
+    fn matching(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.links.len().min(26 * 26))
+            .filter(|&index| self.label(index).starts_with(&self.typed))
+    }
+}
+
+/// Popup asking which of several pages to open, e.g. which section to open an ambiguous bare page
+/// name (like `printf`) from, or which of the pages found by [`apropos`]
+#[derive(Debug)]
+struct PageChooser {
+    title: String,
+    /// `(name, section, description)` of each page to choose from
+    pages: Vec<(String, String, String)>,
+    selected: usize,
+}
+
+impl PageChooser {
+    /// Draws the popup over the center of `area`.
+    fn render(&self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .pages
+            .iter()
+            .map(|(name, section_number, description)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!(" {name}({section_number})"), Style::new().bold()),
+                    Span::raw(format!("  {description} ")),
+                ]))
+            })
+            .collect();
+        let width = items.iter().map(ListItem::width).max().unwrap_or_default() as u16 + 2;
+        let popup_area = centered_rect(area, width, items.len() as u16 + 2);
+        let mut list_state = ListState::default().with_selected(Some(self.selected));
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(self.title.as_str())
+                        .title_alignment(Alignment::Center),
+                )
+                .highlight_style(Style::new().reversed()),
+            popup_area,
+            &mut list_state,
+        );
+    }
+}
+
+/// A page an in-process link jump was made from (see [`App::follow_link`]), along with where it was
+/// left.
+#[derive(Debug, Default)]
+struct VisitedPage {
+    page_id: String,
+    content: String,
+    scroll: usize,
+    marks: HashMap<char, usize>,
+    previous_position: Option<usize>,
+}
+
+/// What to do with the mark named by the next key
+#[derive(Clone, Copy, Debug)]
+enum MarkCommand {
+    /// Record the scroll position (after `m`)
+    Set,
+    /// Jump back to the recorded scroll position (after a backtick)
+    Jump,
+}
+
+/// What (if anything) the user is typing into the bottom line of the screen
 #[derive(Debug, Default, PartialEq, Eq)]
-enum SearchMode {
+enum InputMode {
     #[default]
-    NoSearch,
-    TypingQuery,
+    Normal,
+    /// Typing a search query (started with `/`)
+    Search,
+    /// Typing a command (started with `:`)
+    Command,
+    /// Typing a filename to save the page as (started with `s`, or `S` to keep its formatting)
+    Save { formatted: bool },
+    /// Typing the section to open the page `name` from (started by Alt+double clicking `name`)
+    Section { name: String },
+    /// Typing a keyword to look up pages about with [`apropos`] (started with `a`)
+    Apropos,
+    /// Asking whether to really quit (with `confirm_quit` set in the [`Config`])
+    ConfirmQuit,
 }
 
-const MAN_PROGRAM: &CStr = c"man";
+/// Program that opens URLs with the user's preferred application
+const URL_OPENER: &str = if cfg!(target_os = "macos") {
+    "open"
+} else {
+    "xdg-open"
+};
+
+/// Longest time between two clicks for them to count as a double click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Longest time between the keys of a sequence like `gg` for them to count as one (vim's default
+/// `timeoutlen`)
+const KEY_SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Pager [`App::open_in_pager`] uses if `$PAGER` isn't set (`-R` to show the page's formatting)
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Editor [`App::edit_source`] uses if neither `$VISUAL` nor `$EDITOR` is set
+const DEFAULT_EDITOR: &str = "vi";
+
+/// How long status line messages are shown for (they're cleared by [`App::tick`], so even without
+/// any input)
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+
+/// How long to wait for input before doing [`App::tick`]'s periodic updates, such as checking
+/// whether the terminal was resized
+const TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Number of columns scrolled horizontally at a time
+const HSCROLL_STEP: u16 = 4;
+
+/// Fewest columns or rows the page is laid out in; smaller terminals just get told they're too
+/// small
+const MIN_TERMINAL_SIZE: u16 = 4;
+
+/// Status `linkman` exits with when the user quits after at least one link jump failed (e.g.
+/// because there's no manual entry for the page). Quitting otherwise exits with 0, and fatal errors
+/// exit with 1.
+///
+/// When `linkman` is run on a page name or by `man` directly, it's `man`'s exit status that's
+/// seen, which `man` derives from ours as its pager.
+pub(crate) const EXIT_LINK_JUMP_FAILED: u8 = 4;
+/// Status a fork child exits with when it fails to exec a nested `linkman` (as shells use for a
+/// command that couldn't be run)
+const EXEC_FAILED_STATUS: i32 = 127;
+
+/// Name of the `man` program looked for on `PATH` (see [`find_man_program`])
+const MAN_PROGRAM: &str = "man";
+/// Environment variable [`find_man_program`] records the path of the `man` program in
+const MAN_PROGRAM_VARIABLE: &str = "LINKMAN_MAN_PROGRAM";
+/// Fallback for when [`env::current_exe`] fails
 const SELF_PROGRAM: &str = "/proc/self/exe";
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use ratatui::{backend::TestBackend, buffer::Cell, style::Modifier};
+
+    use super::*;
+
+    /// Plays back a script of events, then quits (with `q`, so scripts should leave the app in
+    /// normal mode).
+    struct Script(VecDeque<Event>);
+
+    impl EventSource for Script {
+        fn next_event(&mut self, _timeout: Duration) -> Result<Option<Event>> {
+            Ok(Some(self.0.pop_front().unwrap_or_else(|| key('q'))))
+        }
+    }
+
+    fn key(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    fn enter() -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+    }
+
+    /// Returns a page of `len` numbered lines, under a `NAME` heading.
+    fn numbered_lines(len: usize) -> String {
+        iter::once(String::from("NAME"))
+            .chain((2..=len).map(|number| format!("line {number}")))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Returns an [`App`] showing `content`, with the default config and preferences, and the
+    /// `width` by `height` terminal it's been drawn on.
+    fn app(content: &str, width: u16, height: u16) -> (App, Terminal<TestBackend>) {
+        let mut app = App::with_state(
+            content.to_owned(),
+            "test(1)",
+            Args::default(),
+            Config::default(),
+            Ok(State::default()),
+        )
+        .unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        play(&mut app, &mut terminal, []);
+
+        (app, terminal)
+    }
+
+    /// Runs `app` on `events`, then quits.
+    fn play(
+        app: &mut App,
+        terminal: &mut Terminal<TestBackend>,
+        events: impl IntoIterator<Item = Event>,
+    ) {
+        app.event_loop(terminal, &mut Script(events.into_iter().collect()))
+            .unwrap();
+    }
+
+    /// Resizes the terminal to `width` by `height`, and tells `app` about it like a real terminal
+    /// would.
+    fn resize(app: &mut App, terminal: &mut Terminal<TestBackend>, width: u16, height: u16) {
+        terminal.backend_mut().resize(width, height);
+        play(app, terminal, [Event::Resize(width, height)]);
+    }
+
+    /// Returns the rows of the screen, as last drawn.
+    fn screen(terminal: &Terminal<TestBackend>) -> Vec<String> {
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(usize::from(buffer.area.width))
+            .map(|row| row.iter().map(Cell::symbol).collect())
+            .collect()
+    }
+
+    /// Returns the text of the selected search match, as last drawn.
+    fn highlighted_match(app: &App, terminal: &Terminal<TestBackend>) -> String {
+        let is_highlighted = |cell: &Cell| {
+            cell.bg == app.config.colors.search_match || cell.modifier.contains(Modifier::REVERSED)
+        };
+        terminal
+            .backend()
+            .buffer()
+            .content
+            .iter()
+            .filter(|cell| is_highlighted(cell))
+            .map(Cell::symbol)
+            .collect()
+    }
+
+    #[test]
+    fn scripted_keys_scroll() {
+        let (mut app, mut terminal) = app(&numbered_lines(100), 80, 24);
+
+        play(
+            &mut app,
+            &mut terminal,
+            [key('j'), key('j'), key('j'), key('k')],
+        );
+        assert_eq!(app.scroll, 2);
+        assert!(screen(&terminal)[1].contains("line 3 "));
+
+        play(&mut app, &mut terminal, [key('G')]);
+        assert_eq!(app.scroll, app.max_scroll());
+    }
+
+    #[test]
+    fn resize_rewraps_to_the_width_inside_the_borders() {
+        let words: Vec<String> = (1..=20).map(|number| format!("word{number}")).collect();
+        let (mut app, mut terminal) = app(&format!("NAME\n{}", words.join(" ")), 80, 24);
+        let lines_at_80 = app.page.lines().len();
+
+        resize(&mut app, &mut terminal, 30, 24);
+        assert_eq!(app.page.wrap_width(), Some(28));
+        assert!(app.page.lines().len() > lines_at_80);
+        let screen = screen(&terminal).join("\n");
+        for word in &words {
+            assert!(screen.contains(&format!("{word} ")), "{word} was cut off");
+        }
+    }
+
+    #[test]
+    fn height_only_resize_then_bottom() {
+        let (mut app, mut terminal) = app(&numbered_lines(100), 80, 24);
+        let wrap_width = app.page.wrap_width();
+
+        resize(&mut app, &mut terminal, 80, 12);
+        assert_eq!(app.height, 12);
+        assert_eq!(app.page.wrap_width(), wrap_width);
+
+        play(&mut app, &mut terminal, [key('G')]);
+        assert_eq!(app.scroll, app.max_scroll());
+    }
+
+    #[test]
+    fn tiny_resize_doesnt_panic() {
+        let (mut app, mut terminal) = app(&numbered_lines(100), 80, 24);
+
+        resize(&mut app, &mut terminal, 1, 1);
+        play(&mut app, &mut terminal, [key('G'), key('j'), key('k')]);
+
+        resize(&mut app, &mut terminal, 80, 24);
+        assert!(screen(&terminal)[0].contains("LinkMan - test(1)"));
+    }
+
+    #[test]
+    fn bottom_of_a_short_page_is_the_top() {
+        let mut app = App::with_state(
+            String::from("NAME\n    short - a short page\n"),
+            "short(1)",
+            Args::default(),
+            Config::default(),
+            Ok(State::default()),
+        )
+        .unwrap();
+        app.height = 50;
+
+        assert_eq!(app.max_scroll(), 0);
+    }
+
+    #[test]
+    fn bottom_shows_the_last_line() {
+        // More lines than a `u16` scroll offset can reach
+        let content = (1..=70_000).map(|n| format!("line {n}\n")).collect();
+        let mut app = App::with_state(
+            content,
+            "lines(1)",
+            Args::default(),
+            Config::default(),
+            Ok(State::default()),
+        )
+        .unwrap();
+        app.handle_resize(80, 24).unwrap();
+
+        app.perform_action(Action::Bottom, None).unwrap();
+        let visible = app.visible_lines();
+        assert_eq!(visible.len(), 21);
+        assert_eq!(app.page.lines()[visible.start], "line 69980");
+        assert_eq!(app.page.lines()[visible.end - 1], "line 70000");
+        assert!(app.position_indicator().starts_with("100%"));
+    }
+
+    #[test]
+    fn taller_terminal_clamps_the_scroll() {
+        let content = (1..=100).map(|n| format!("line {n}\n")).collect();
+        let mut app = App::with_state(
+            content,
+            "lines(1)",
+            Args::default(),
+            Config::default(),
+            Ok(State::default()),
+        )
+        .unwrap();
+        app.handle_resize(80, 20).unwrap();
+        app.scroll = app.max_scroll();
+
+        app.handle_resize(80, 40).unwrap();
+        assert_eq!(app.scroll, app.max_scroll());
+        assert_eq!(app.page.lines().len(), 100);
+    }
+
+    #[test]
+    fn narrower_terminal_rewraps() {
+        let mut app = App::with_state(
+            "word ".repeat(40),
+            "words(1)",
+            Args::default(),
+            Config::default(),
+            Ok(State::default()),
+        )
+        .unwrap();
+        app.handle_resize(80, 20).unwrap();
+        let lines_at_80 = app.page.lines().len();
+
+        app.handle_resize(40, 20).unwrap();
+        assert!(app.page.lines().len() > lines_at_80);
+        assert!(
+            app.page
+                .lines()
+                .iter()
+                .all(|line| line.trim_end().len() <= 40)
+        );
+    }
+
+    #[test]
+    fn search_highlight_follows_rewrapping() {
+        let (mut app, mut terminal) = app(
+            "NAME\nthe quick brown fox jumps over the lazy dog to find a needle",
+            80,
+            24,
+        );
+        play(
+            &mut app,
+            &mut terminal,
+            "/needle".chars().map(key).chain([enter()]),
+        );
+
+        resize(&mut app, &mut terminal, 24, 24);
+        assert_eq!(highlighted_match(&app, &terminal), "needle");
+    }
+
+    #[test]
+    fn search_highlight_covers_characters_that_change_length_when_lowercased() {
+        // The Kelvin sign lowercases to a `k` two bytes shorter than it
+        let (mut app, mut terminal) = app("NAME\n\u{212A}elvin scale", 80, 24);
+        play(
+            &mut app,
+            &mut terminal,
+            "/kelvin".chars().map(key).chain([enter()]),
+        );
+
+        assert_eq!(highlighted_match(&app, &terminal), "\u{212A}elvin");
+    }
+}
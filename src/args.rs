@@ -0,0 +1,245 @@
+use anyhow::{Result, anyhow, bail};
+
+use crate::ManPageInfo;
+use crate::man_page_info::validate_section;
+
+/// Passed to the `linkman` that `man` runs as its pager (see [`crate::app::exec_self`]), telling
+/// it not to re-run `man` itself.
+pub(crate) const SUBSEQUENT_RUN_FLAG: &str = "--subsequent-run";
+/// Passed (once per page) to a nested `linkman` to tell it which pages were jumped through to
+/// reach it.
+pub(crate) const PARENT_FLAG: &str = "--parent";
+/// Passed to a nested `linkman` to tell it the reference of the page it's shown (e.g. `open(2)`),
+/// which its header may only give differently (e.g. `OPEN(2)`).
+pub(crate) const PAGE_REF_FLAG: &str = "--page-ref";
+/// Draws the TUI on the main screen rather than the alternate screen, so what was on screen stays
+/// in the terminal's scrollback.
+const NO_ALT_SCREEN_FLAG: &str = "--no-alt-screen";
+/// Starts without mouse capture (for terminals that don't support it), following links with the
+/// keyboard instead.
+pub(crate) const NO_MOUSE_FLAG: &str = "--no-mouse";
+/// Shows pages without color (as does setting `NO_COLOR`), using only bold, underline, and reverse
+/// video to set things apart.
+pub(crate) const NO_COLOR_FLAG: &str = "--no-color";
+/// Follows links by swapping pages within one `linkman`, instead of nesting another for each.
+const IN_PROCESS_FLAG: &str = "--in-process";
+/// Prints the page as it would be laid out in the TUI, instead of showing it.
+const DUMP_FLAG: &str = "--dump";
+/// Runs the given `man` program, instead of the one on `PATH`.
+const MAN_BINARY_FLAG: &str = "--man-binary";
+/// Forces the width (in columns) pages are rendered at, instead of fitting them to the terminal.
+const MAN_WIDTH_FLAG: &str = "--man-width";
+/// Reads the page from the given file, instead of from stdin.
+const FILE_FLAG: &str = "--file";
+/// Shows the text read as it is, rather than as a man page to be formatted by `man`.
+const RAW_FLAG: &str = "--raw";
+
+/// Parsed command-line arguments
+#[derive(Debug, Default)]
+pub(crate) struct Args {
+    /// Whether this is a `--subsequent-run` (i.e. `man` ran us as its pager)
+    pub(crate) subsequent_run: bool,
+    /// Identifiers of the pages that link jumps went through to reach this one, most recent first
+    pub(crate) parents: Vec<String>,
+    /// Reference of the page shown, if `man` was asked for it (`--page-ref REF`)
+    pub(crate) page_ref: Option<String>,
+    /// Width to render pages at (`--man-width N`). This is passed on by setting `MANWIDTH`, which
+    /// nested (`--subsequent-run`) instances inherit along with the rest of the environment, so
+    /// the flag itself only needs to be given to the first run.
+    pub(crate) man_width: Option<u16>,
+    /// `man` program to run (`--man-binary PATH`). Like [`Args::man_width`], this is passed on
+    /// through the environment (see [`crate::app::find_man_program`]).
+    pub(crate) man_binary: Option<String>,
+    /// Whether to stay off the alternate screen (`--no-alt-screen`)
+    pub(crate) no_alt_screen: bool,
+    /// Whether to start without mouse capture (`--no-mouse`)
+    pub(crate) no_mouse: bool,
+    /// Whether to show pages without color (`--no-color`)
+    pub(crate) no_color: bool,
+    /// Whether to follow links within this process (`--in-process`), keeping a stack of the pages
+    /// followed from to go back to
+    pub(crate) in_process: bool,
+    /// Whether to print the laid out page and exit (`--dump`)
+    pub(crate) dump: bool,
+    /// File to read the page from (`--file PATH`), instead of stdin. The page is shown as it was
+    /// formatted when saved, without having `man` lay it out again for the terminal's width.
+    pub(crate) file: Option<String>,
+    /// Whether the text read isn't a man page (`--raw`), so there's no page to have `man` format
+    /// for the terminal's width. Text whose first word isn't a man reference is treated this way
+    /// regardless.
+    pub(crate) raw: bool,
+    /// Positional arguments naming a man page to open (instead of reading one from stdin)
+    pub(crate) page: Vec<String>,
+}
+
+impl Args {
+    /// Parses `args` (which should not include the program name).
+    pub(crate) fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                SUBSEQUENT_RUN_FLAG => parsed.subsequent_run = true,
+                NO_ALT_SCREEN_FLAG => parsed.no_alt_screen = true,
+                NO_MOUSE_FLAG => parsed.no_mouse = true,
+                NO_COLOR_FLAG => parsed.no_color = true,
+                IN_PROCESS_FLAG => parsed.in_process = true,
+                DUMP_FLAG => parsed.dump = true,
+                RAW_FLAG => parsed.raw = true,
+                PARENT_FLAG => parsed.parents.push(
+                    args.next()
+                        .ok_or_else(|| anyhow!("{PARENT_FLAG} requires a value"))?,
+                ),
+                PAGE_REF_FLAG => {
+                    parsed.page_ref = Some(
+                        args.next()
+                            .ok_or_else(|| anyhow!("{PAGE_REF_FLAG} requires a value"))?,
+                    )
+                }
+                MAN_BINARY_FLAG => {
+                    parsed.man_binary = Some(
+                        args.next()
+                            .ok_or_else(|| anyhow!("{MAN_BINARY_FLAG} requires a value"))?,
+                    )
+                }
+                FILE_FLAG => {
+                    parsed.file = Some(
+                        args.next()
+                            .ok_or_else(|| anyhow!("{FILE_FLAG} requires a value"))?,
+                    )
+                }
+                MAN_WIDTH_FLAG => {
+                    let width = args
+                        .next()
+                        .ok_or_else(|| anyhow!("{MAN_WIDTH_FLAG} requires a value"))?;
+                    // Nothing fits in zero columns (and `man` ignores a `MANWIDTH` of 0 anyway)
+                    parsed.man_width = Some(
+                        width
+                            .parse()
+                            .ok()
+                            .filter(|&width: &u16| width != 0)
+                            .ok_or_else(|| {
+                                anyhow!(
+                                    "{MAN_WIDTH_FLAG} expects a number of columns (1 to {}), got '{width}'",
+                                    u16::MAX
+                                )
+                            })?,
+                    );
+                }
+                _ if arg.starts_with('-') => bail!("Unrecognized argument: {arg}"),
+                _ if parsed.page.len() == 2 => {
+                    bail!("Expected at most two arguments (a man page name and section)")
+                }
+                _ => parsed.page.push(arg),
+            }
+        }
+
+        if parsed.file.is_some() && !parsed.page.is_empty() {
+            bail!("{FILE_FLAG} can't be combined with a man page name");
+        }
+
+        Ok(parsed)
+    }
+
+    /// Returns the flags to pass on to nested `linkman`s (see [`crate::app::exec_self`]) so they
+    /// behave the same way as this one.
+    pub(crate) fn nested_flags(&self) -> Vec<&'static str> {
+        let mut flags = Vec::new();
+        if self.no_alt_screen {
+            flags.push(NO_ALT_SCREEN_FLAG);
+        }
+        if self.no_mouse {
+            flags.push(NO_MOUSE_FLAG);
+        }
+        if self.no_color {
+            flags.push(NO_COLOR_FLAG);
+        }
+        if self.in_process {
+            flags.push(IN_PROCESS_FLAG);
+        }
+        if self.dump {
+            flags.push(DUMP_FLAG);
+        }
+
+        flags
+    }
+
+    /// Returns the man page named by the positional arguments (`NAME`, `NAME SECTION`, or
+    /// `SECTION NAME`), if there are any.
+    pub(crate) fn page_info(&self) -> Result<Option<ManPageInfo<'_>>> {
+        let (name, section_number) = match self.page.as_slice() {
+            [] => return Ok(None),
+            [name] => (name.as_str(), ""),
+            // Only read the arguments as `SECTION NAME` when that's the sole sensible order, so
+            // names like `2to3` still work as `NAME SECTION`
+            [section_number, name]
+                if validate_section(section_number).is_ok() && validate_section(name).is_err() =>
+            {
+                (name.as_str(), section_number.as_str())
+            }
+            [name, section_number, ..] => (name.as_str(), section_number.as_str()),
+        };
+
+        Ok(Some(ManPageInfo::new(name, section_number)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        Args::parse(args.iter().map(|&arg| arg.to_owned()))
+    }
+
+    /// Returns the page named by `args`, formatted as `NAME(SECTION)`.
+    fn page(args: &[&str]) -> String {
+        parse(args)
+            .unwrap()
+            .page_info()
+            .unwrap()
+            .map(|info| info.to_string())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn pages() {
+        assert_eq!(page(&[]), "");
+        assert_eq!(page(&["open"]), "open");
+        assert_eq!(page(&["open", "2"]), "open(2)");
+        assert_eq!(page(&["2", "open"]), "open(2)");
+        assert_eq!(page(&["3p", "printf"]), "printf(3p)");
+    }
+
+    #[test]
+    fn names_starting_with_digits() {
+        assert_eq!(page(&["2to3", "1"]), "2to3(1)");
+    }
+
+    #[test]
+    fn flags() {
+        let args = parse(&[SUBSEQUENT_RUN_FLAG, PARENT_FLAG, "open(2)", "read"]).unwrap();
+        assert!(args.subsequent_run);
+        assert_eq!(args.parents, ["open(2)"]);
+        assert_eq!(args.page, ["read"]);
+    }
+
+    #[test]
+    fn man_widths() {
+        assert_eq!(parse(&[]).unwrap().man_width, None);
+        assert_eq!(parse(&["--man-width", "72"]).unwrap().man_width, Some(72));
+        assert!(parse(&["--man-width", "0"]).is_err());
+        assert!(parse(&["--man-width", "-1"]).is_err());
+        assert!(parse(&["--man-width", "wide"]).is_err());
+        assert!(parse(&["--man-width"]).is_err());
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert!(parse(&["--bogus"]).is_err());
+        assert!(parse(&[PARENT_FLAG]).is_err());
+        assert!(parse(&["a", "b", "c"]).is_err());
+        assert!(parse(&["foo/bar"]).unwrap().page_info().is_err());
+    }
+}
@@ -0,0 +1,30 @@
+use std::fmt::{self, Debug, Formatter};
+
+use anyhow::Result;
+
+/// Lazily-connected handle to the system clipboard.
+///
+/// The connection is kept open once made since, on some platforms (e.g. X11), the copied text is
+/// only available for as long as the process that copied it keeps its connection open.
+#[derive(Default)]
+pub(crate) struct Clipboard(Option<arboard::Clipboard>);
+
+impl Clipboard {
+    /// Copies `text` to the system clipboard, connecting to it first if needed.
+    pub(crate) fn copy(&mut self, text: impl Into<String>) -> Result<()> {
+        let clipboard = match &mut self.0 {
+            Some(clipboard) => clipboard,
+            None => self.0.insert(arboard::Clipboard::new()?),
+        };
+
+        Ok(clipboard.set_text(text.into())?)
+    }
+}
+
+impl Debug for Clipboard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Clipboard")
+            .field(&self.0.as_ref().map(|_| "connected"))
+            .finish()
+    }
+}
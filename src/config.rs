@@ -0,0 +1,574 @@
+use std::{
+    env,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    style::Color,
+};
+use serde::{Deserialize, Deserializer, de};
+
+/// User configuration, read from `$XDG_CONFIG_HOME/linkman/config.toml` (or
+/// `~/.config/linkman/config.toml`). Every setting is optional and falls back to its default.
+///
+/// ```toml
+/// scroll_lines_per_wheel = 5
+/// highlight_synopsis = false
+/// confirm_quit = true
+///
+/// [keys]
+/// scroll_down = ["Ctrl-n", "Down"]
+/// scroll_up = ["Ctrl-p", "Up"]
+/// page_down = ["Ctrl-v", "PageDown"]
+/// page_up = ["Alt-v", "PageUp"]
+/// search = ["Ctrl-s", "/"]
+///
+/// [colors]
+/// link = "blue"
+/// current_match = "#ffaf00"
+/// matches = "darkgray"
+/// heading = "lightblue"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+    /// Lines scrolled by each notch of the mouse wheel (the keys always scroll by one)
+    pub(crate) scroll_lines_per_wheel: usize,
+    /// Whether to color the SYNOPSIS section (see [`Colors::synopsis`])
+    pub(crate) highlight_synopsis: bool,
+    /// Whether to ask before quitting to the shell, in case a quit key was pressed by accident
+    pub(crate) confirm_quit: bool,
+    pub(crate) keys: KeyMap,
+    pub(crate) colors: Colors,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scroll_lines_per_wheel: 3,
+            highlight_synopsis: true,
+            confirm_quit: false,
+            keys: KeyMap::default(),
+            colors: Colors::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration file, or returns the default configuration if there isn't one.
+    pub(crate) fn load() -> Result<Self> {
+        let Some(path) = config_path() else {
+            return Ok(Self::default());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)
+                .with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+}
+
+/// Returns where the configuration file should be, if there's anywhere it could be.
+fn config_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_dir.join("linkman").join("config.toml"))
+}
+
+/// Something a configurable key does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Quit,
+    ScrollDown,
+    ScrollUp,
+    PageDown,
+    PageUp,
+    HalfPageDown,
+    HalfPageUp,
+    ScrollLeft,
+    ScrollRight,
+    Top,
+    Bottom,
+    Search,
+    Back,
+    Forward,
+}
+
+/// The keys bound to each [`Action`].
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct KeyMap {
+    quit: Vec<KeyBinding>,
+    scroll_down: Vec<KeyBinding>,
+    scroll_up: Vec<KeyBinding>,
+    page_down: Vec<KeyBinding>,
+    page_up: Vec<KeyBinding>,
+    half_page_down: Vec<KeyBinding>,
+    half_page_up: Vec<KeyBinding>,
+    scroll_left: Vec<KeyBinding>,
+    scroll_right: Vec<KeyBinding>,
+    top: Vec<KeyBinding>,
+    bottom: Vec<KeyBinding>,
+    search: Vec<KeyBinding>,
+    back: Vec<KeyBinding>,
+    forward: Vec<KeyBinding>,
+}
+
+impl KeyMap {
+    /// Returns the action bound to `key`, if any. If a key is bound to more than one action, the
+    /// one listed first in [`Action`] wins.
+    pub(crate) fn action(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings()
+            .into_iter()
+            .find(|(_, bindings)| bindings.iter().any(|binding| binding.matches(key)))
+            .map(|(action, _)| action)
+    }
+
+    /// Returns the keys bound to `action`, formatted for display (e.g. `j / Down`).
+    pub(crate) fn describe(&self, action: Action) -> String {
+        self.bindings()
+            .into_iter()
+            .find(|&(bound_action, _)| bound_action == action)
+            .map(|(_, bindings)| {
+                bindings
+                    .iter()
+                    .map(KeyBinding::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" / ")
+            })
+            .unwrap_or_default()
+    }
+
+    fn bindings(&self) -> [(Action, &[KeyBinding]); 14] {
+        [
+            (Action::Quit, &self.quit),
+            (Action::ScrollDown, &self.scroll_down),
+            (Action::ScrollUp, &self.scroll_up),
+            (Action::PageDown, &self.page_down),
+            (Action::PageUp, &self.page_up),
+            (Action::HalfPageDown, &self.half_page_down),
+            (Action::HalfPageUp, &self.half_page_up),
+            (Action::ScrollLeft, &self.scroll_left),
+            (Action::ScrollRight, &self.scroll_right),
+            (Action::Top, &self.top),
+            (Action::Bottom, &self.bottom),
+            (Action::Search, &self.search),
+            (Action::Back, &self.back),
+            (Action::Forward, &self.forward),
+        ]
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let keys = |bindings: &[&str]| {
+            bindings
+                .iter()
+                .map(|binding| binding.parse().expect("default key bindings are valid"))
+                .collect()
+        };
+
+        Self {
+            quit: keys(&["q"]),
+            scroll_down: keys(&["j", "Down"]),
+            scroll_up: keys(&["k", "Up"]),
+            page_down: keys(&["Space", "PageDown", "Ctrl-f"]),
+            page_up: keys(&["PageUp", "Ctrl-b"]),
+            half_page_down: keys(&["Ctrl-d"]),
+            half_page_up: keys(&["Ctrl-u"]),
+            scroll_left: keys(&["h", "Left"]),
+            scroll_right: keys(&["l", "Right"]),
+            top: keys(&["g", "0"]),
+            bottom: keys(&["G"]),
+            search: keys(&["/"]),
+            // Back is like vim's jumplist, but forward can't be its Ctrl+I: terminals send that
+            // as Tab, which selects links
+            back: keys(&["Ctrl-o", "Backspace", "Alt-Left"]),
+            forward: keys(&["Alt-Right"]),
+        }
+    }
+}
+
+/// A key (with modifiers), written like `q`, `G`, `Ctrl-f`, `Alt+v`, or `PageDown`.
+///
+/// Shift is folded into the key for characters (so `Shift-g` and `G` are the same binding), since
+/// terminals don't report it consistently. Characters with Ctrl are always lowercase, since
+/// terminals send the same thing for `Ctrl-f` and `Ctrl-F`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) => Self {
+                code: KeyCode::Char(if modifiers.contains(KeyModifiers::CONTROL) {
+                    c.to_ascii_lowercase()
+                } else if modifiers.contains(KeyModifiers::SHIFT) {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => Self { code, modifiers },
+        }
+    }
+
+    /// Returns whether `key` was this key (with exactly these modifiers) being pressed.
+    pub(crate) fn matches(&self, key: &KeyEvent) -> bool {
+        *self == Self::new(key.code, key.modifiers)
+    }
+}
+
+/// Names for non-character keys (the first name for each key is the one displayed)
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("Space", KeyCode::Char(' ')),
+    ("Down", KeyCode::Down),
+    ("Up", KeyCode::Up),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("PgDn", KeyCode::PageDown),
+    ("PageDown", KeyCode::PageDown),
+    ("PgUp", KeyCode::PageUp),
+    ("PageUp", KeyCode::PageUp),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+];
+
+impl FromStr for KeyBinding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = s;
+
+        // Peel off modifier prefixes (the key itself may be `-` or `+`, as in `Ctrl--`)
+        while let Some((modifier, rest)) = key.split_once(['-', '+'])
+            && !rest.is_empty()
+        {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("Unknown modifier '{modifier}' in key '{s}'"),
+            };
+            key = rest;
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => KEY_NAMES
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|&(_, code)| code)
+                .or_else(|| {
+                    key.strip_prefix(['F', 'f'])
+                        .and_then(|number| number.parse().ok())
+                        .map(KeyCode::F)
+                })
+                .ok_or_else(|| anyhow!("Unknown key '{key}' in '{s}'"))?,
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("Shift+")?;
+        }
+
+        match KEY_NAMES.iter().find(|&&(_, code)| code == self.code) {
+            Some((name, _)) => f.write_str(name),
+            None => match self.code {
+                // Ctrl characters are always lowercase, so they can be shown like `Ctrl+F`
+                KeyCode::Char(c) if self.modifiers == KeyModifiers::CONTROL => {
+                    write!(f, "{}", c.to_ascii_uppercase())
+                }
+                KeyCode::Char(c) => write!(f, "{c}"),
+                KeyCode::F(number) => write!(f, "F{number}"),
+                code => write!(f, "{code:?}"),
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Colors used to highlight parts of the page.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Colors {
+    /// Color of (underlined) man references
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) link: Color,
+    /// Background of the link selected with Tab/Shift+Tab
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) selected_link: Color,
+    /// Background of the current search match
+    #[serde(alias = "current_match", deserialize_with = "deserialize_color")]
+    pub(crate) search_match: Color,
+    /// Background of the other search matches (which are otherwise shown in reverse video)
+    #[serde(deserialize_with = "deserialize_some_color")]
+    pub(crate) matches: Option<Color>,
+    /// Color of section headings (which are otherwise left as the page formats them)
+    #[serde(deserialize_with = "deserialize_some_color")]
+    pub(crate) heading: Option<Color>,
+    /// Color of messages on the status line
+    #[serde(deserialize_with = "deserialize_some_color")]
+    pub(crate) status: Option<Color>,
+    /// Color of the SYNOPSIS section's text (where the page doesn't color it itself)
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) synopsis: Color,
+    /// Background of the labels `f` puts on links
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) link_hint: Color,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Self {
+            link: Color::Cyan,
+            selected_link: Color::Cyan,
+            search_match: Color::Yellow,
+            matches: None,
+            heading: None,
+            status: None,
+            synopsis: Color::Green,
+            link_hint: Color::Yellow,
+        }
+    }
+}
+
+/// Deserializes a [`Color`] from a name (e.g. `cyan`, `lightred`), a `#rrggbb` hex code, or an ANSI
+/// color index.
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let color = String::deserialize(deserializer)?;
+    color
+        .parse()
+        .map_err(|_| de::Error::custom(format!("invalid color '{color}'")))
+}
+
+/// Deserializes a color (see [`deserialize_color`]) for a setting that's unset by default.
+fn deserialize_some_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Color>, D::Error> {
+    deserialize_color(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(s: &str) -> KeyBinding {
+        s.parse().unwrap()
+    }
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn modifiers() {
+        assert_eq!(
+            binding("Ctrl-x"),
+            KeyBinding::new(KeyCode::Char('x'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(binding("ctrl+x"), binding("Ctrl-x"));
+        assert_eq!(binding("Ctrl-X"), binding("Ctrl-x"));
+        assert_eq!(binding("Control-x"), binding("Ctrl-x"));
+        assert_eq!(
+            binding("Alt-Y"),
+            KeyBinding::new(KeyCode::Char('Y'), KeyModifiers::ALT)
+        );
+        assert_eq!(binding("Meta-Y"), binding("Alt-Y"));
+        assert_eq!(
+            binding("Ctrl-Alt-d"),
+            KeyBinding::new(
+                KeyCode::Char('d'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+        assert_eq!(
+            binding("Ctrl--"),
+            KeyBinding::new(KeyCode::Char('-'), KeyModifiers::CONTROL)
+        );
+    }
+
+    #[test]
+    fn shift_folds_into_characters() {
+        assert_eq!(binding("Shift-a"), binding("A"));
+        assert_eq!(binding("Shift-A"), binding("A"));
+        assert!(binding("G").matches(&key(KeyCode::Char('G'), KeyModifiers::SHIFT)));
+        assert!(binding("G").matches(&key(KeyCode::Char('G'), KeyModifiers::NONE)));
+        assert!(!binding("g").matches(&key(KeyCode::Char('G'), KeyModifiers::SHIFT)));
+
+        // Other keys keep Shift as a modifier
+        assert_eq!(
+            binding("Shift-Tab"),
+            KeyBinding::new(KeyCode::Tab, KeyModifiers::SHIFT)
+        );
+    }
+
+    #[test]
+    fn named_keys() {
+        assert_eq!(binding("Space"), binding(" "));
+        assert_eq!(binding("PgDn"), binding("PageDown"));
+        assert_eq!(binding("pagedown"), binding("PageDown"));
+        assert_eq!(
+            binding("Esc"),
+            KeyBinding::new(KeyCode::Esc, KeyModifiers::NONE)
+        );
+        assert_eq!(
+            binding("F5"),
+            KeyBinding::new(KeyCode::F(5), KeyModifiers::NONE)
+        );
+    }
+
+    #[test]
+    fn invalid_bindings() {
+        assert!("".parse::<KeyBinding>().is_err());
+        assert!("Hyper-x".parse::<KeyBinding>().is_err());
+        assert!("Ctrl-Nope".parse::<KeyBinding>().is_err());
+        assert!("Fx".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "q",
+            "G",
+            "Ctrl+F",
+            "Alt+v",
+            "Alt+Y",
+            "Ctrl+Alt+d",
+            "PgDn",
+            "Space",
+            "Shift+Tab",
+            "F5",
+        ] {
+            assert_eq!(binding(s).to_string(), s);
+        }
+        for s in [
+            "Ctrl-f",
+            "Ctrl-F",
+            "Alt-y",
+            "Alt-Y",
+            "Shift-a",
+            "Ctrl-Shift-x",
+        ] {
+            assert_eq!(binding(&binding(s).to_string()), binding(s));
+        }
+    }
+
+    #[test]
+    fn key_map() {
+        let keys = KeyMap::default();
+        assert_eq!(
+            keys.action(&key(KeyCode::Char('d'), KeyModifiers::CONTROL)),
+            Some(Action::HalfPageDown)
+        );
+        assert_eq!(
+            keys.action(&key(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+            Some(Action::Bottom)
+        );
+        assert_eq!(
+            keys.action(&key(KeyCode::Char('z'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(keys.describe(Action::PageDown), "Space / PgDn / Ctrl+F");
+    }
+
+    #[test]
+    fn default_keys_are_distinguishable() {
+        let keys = KeyMap::default();
+        assert_eq!(
+            keys.action(&key(KeyCode::Right, KeyModifiers::ALT)),
+            Some(Action::Forward)
+        );
+        // What terminals send for Ctrl+I
+        assert_eq!(keys.action(&key(KeyCode::Tab, KeyModifiers::NONE)), None);
+
+        // No key is bound to more than one action
+        let bindings = keys.bindings();
+        for (i, (action, keys)) in bindings.iter().enumerate() {
+            for (other_action, other_keys) in &bindings[i + 1..] {
+                assert!(
+                    keys.iter().all(|binding| !other_keys.contains(binding)),
+                    "{action:?} and {other_action:?} share a key"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn config_files() {
+        let config: Config = toml::from_str(
+            r##"
+            [keys]
+            quit = ["Ctrl-c"]
+
+            [colors]
+            link = "#ff0000"
+            link_hint = "lightmagenta"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(
+            config
+                .keys
+                .action(&key(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            config
+                .keys
+                .action(&key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            None
+        );
+        // Unset bindings keep their defaults
+        assert_eq!(
+            config.keys.describe(Action::Top),
+            KeyMap::default().describe(Action::Top)
+        );
+        assert_eq!(config.colors.link, Color::Rgb(0xff, 0, 0));
+        assert_eq!(config.colors.link_hint, Color::LightMagenta);
+
+        assert!(toml::from_str::<Config>("[keys]\nquit = [\"Hyper-q\"]").is_err());
+        assert!(toml::from_str::<Config>("[keys]\nfly = [\"f\"]").is_err());
+        assert!(toml::from_str::<Config>("[colors]\nlink = \"blurple\"").is_err());
+    }
+}
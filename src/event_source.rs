@@ -0,0 +1,27 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event};
+
+/// Where [`crate::app::App::run`] gets its input events from.
+///
+/// This is the terminal (see [`TerminalEvents`]) when running for real, but can be anything that
+/// produces [`Event`]s, e.g. a scripted sequence of keypresses and clicks.
+pub(crate) trait EventSource {
+    /// Waits up to `timeout` for the next event, returning `None` if there wasn't one in time.
+    fn next_event(&mut self, timeout: Duration) -> Result<Option<Event>>;
+}
+
+/// Reads events from the terminal, through crossterm.
+#[derive(Debug, Default)]
+pub(crate) struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, timeout: Duration) -> Result<Option<Event>> {
+        Ok(if event::poll(timeout)? {
+            Some(event::read()?)
+        } else {
+            None
+        })
+    }
+}
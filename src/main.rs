@@ -1,48 +1,214 @@
 mod app;
+mod args;
+mod clipboard;
+mod config;
+mod event_source;
 mod man_page_info;
+mod page;
+mod page_cache;
+mod state;
+mod terminal_guard;
 mod text_handling;
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use app::App;
+use args::Args;
+use config::Config;
+use event_source::TerminalEvents;
 use man_page_info::ManPageInfo;
-use std::{env, io};
+use std::{
+    env, fs,
+    io::{self, Write},
+    os::fd::RawFd,
+    process::ExitCode,
+};
+use terminal_guard::TerminalGuard;
 
-fn main() -> Result<()> {
-    let content = io::read_to_string(io::stdin())?;
-    let man_string = text_handling::get_man_string(&content)?;
+fn main() -> Result<ExitCode> {
+    let args = Args::parse(env::args().skip(1))?;
+
+    // A forced width takes the place of an inherited `MANWIDTH`, which `set_man_width_variable`
+    // (here and in every nested `linkman`) then leaves alone
+    if let Some(man_width) = args.man_width {
+        // SAFETY: This program has no "threads" in the sense that no two Linux tasks will ever share the same virtual memory space,
+        // so this is safe.
+        unsafe { env::set_var("MANWIDTH", man_width.to_string()) };
+    }
+
+    // Fail now, rather than on the first link jump, if there's no `man` to run (only dumping a
+    // piped or saved page can do without it)
+    if !args.dump || !args.page.is_empty() {
+        // SAFETY: This program has no "threads" in the sense that no two Linux tasks will ever share the same virtual memory space,
+        // so this is safe.
+        unsafe { app::find_man_program(args.man_binary.as_deref()) }?;
+    }
+
+    // If we were given a man page to open, have `man` open it (with us as the pager) instead of
+    // reading a page from stdin
+    if !args.subsequent_run
+        && let Some(man_page_info) = args.page_info()?
+    {
+        // SAFETY: This program has no "threads" in the sense that no two Linux tasks will ever share the same virtual memory space,
+        // so this is safe.
+        unsafe { app::set_man_width_variable() }?;
+        app::exec_self(&man_page_info, &[], &args.nested_flags())?;
+    }
+
+    let content = match &args.file {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the page from {path}"))?,
+        None => io::read_to_string(io::stdin())?,
+    };
+
+    // Dump the page as given, rather than having `man` format it again for the terminal's width.
+    // This comes before looking for the page's identifier, so empty input dumps as nothing.
+    if args.dump {
+        dump(&content, &mut io::stdout().lock())?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let man_string = if args.raw {
+        None
+    } else {
+        Some(text_handling::get_man_string(&content)?)
+    };
+    let man_page_info = man_string
+        .as_deref()
+        .and_then(|man_string| ManPageInfo::try_from(man_string).ok());
 
     /* First, check if we've received `--subsequent-run`. If we have, everything is dandy. If we
      * haven't, we'll need to parse the man page and section we were run on, set MANWIDTH, and
-     * rerun the command. If we don't, the alignment will be wonky.
+     * rerun the command. If we don't, the alignment will be wonky. A page read from a file was laid
+     * out when it was saved, and text that isn't a man page has no page to rerun `man` on, so
+     * they're shown as they are.
      */
-    if env::args().skip(1).all(|s| &s != "--subsequent-run") {
+    if !args.subsequent_run
+        && args.file.is_none()
+        && let Some(man_page_info) = &man_page_info
+    {
         // SAFETY: This program has no "threads" in the sense that no two Linux tasks will ever share the same virtual memory space,
         // so this is safe.
         unsafe { app::set_man_width_variable() }?;
-        let man_page_info = ManPageInfo::try_from(man_string.as_str())?;
+        app::exec_self(man_page_info, &[], &args.nested_flags())?;
+    }
+
+    // Pages `man` ran us on are named by the reference we asked it for if we did, or by their first
+    // word (even if it isn't a valid reference), but other text is named after where it came from
+    let page_id = match man_string {
+        Some(man_string) if args.subsequent_run || man_page_info.is_some() => {
+            args.page_ref.clone().unwrap_or(man_string)
+        }
+        _ => args.file.clone().unwrap_or_else(|| String::from("stdin")),
+    };
+
+    take_terminal_input()?;
+
+    // Read the config before taking over the terminal, so errors in it are readable
+    let config = Config::load()?;
 
-        app::exec_self(&man_page_info)?;
+    let alt_screen = !args.no_alt_screen;
+    let app = App::new(content, page_id, args, config)?;
+
+    // Setup terminal. It's restored when `terminal` is dropped, including when `run` returns an
+    // error (which is then printed to the restored terminal) or panics.
+    let mut terminal = TerminalGuard::new(alt_screen)?;
+    app.run(&mut terminal, &mut TerminalEvents)
+}
+
+/// Prints the plain text of the (ANSI-formatted) man page `content`, wrapped the same way the TUI
+/// wraps it, to `out` (stdout, outside of tests).
+///
+/// The width is taken from `MANWIDTH`, or else `COLUMNS` (less the TUI's borders), or else 78 (see
+/// [`app::set_man_width_variable`]), but never from the terminal, so the output only depends on the
+/// input and the environment.
+fn dump(content: &str, out: &mut impl Write) -> Result<()> {
+    // An empty page would otherwise lay out as a single blank line
+    if content.is_empty() {
+        return Ok(());
     }
 
-    // Replace stdin fd with PTY/TTY fd from stderr
-    if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDIN_FILENO) } < 0 {
-        // SAFETY: Simple dup2 call made with two valid fds. There is valid error checking: program will panic if dup2 fails
-        panic!("libc::dup2 call (to put /dev/tty fd over stdin fd) failed");
+    let width = env::var("MANWIDTH")
+        .ok()
+        .and_then(|s| s.parse::<u16>().ok())
+        .or_else(|| {
+            env::var("COLUMNS")
+                .ok()
+                .and_then(|s| s.parse::<u16>().ok())
+                .map(|columns| columns.saturating_sub(2))
+        })
+        .unwrap_or(78);
+    let (text, _) = text_handling::format_content(content, Some(width.into()))?;
+
+    for line in text_handling::plain_lines(&text) {
+        match writeln!(out, "{line}") {
+            Ok(()) => (),
+            // The reader (e.g. `head`) has all it wants
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            Err(e) => return Err(e).context("Failed to write the page to stdout"),
+        }
     }
 
-    // Setup terminal
-    let mut terminal = ratatui::init();
+    Ok(())
+}
 
-    let app = App::new(content, man_string);
-    let res = app.run(&mut terminal);
+/// Fails with an explanation if `fd` (standard error, outside of tests) isn't a terminal, which
+/// LinkMan needs to run in.
+fn require_terminal(fd: RawFd) -> Result<()> {
+    // SAFETY: isatty only inspects the (always valid to pass) fd number
+    if unsafe { libc::isatty(fd) } == 0 {
+        bail!(
+            "LinkMan needs a terminal to run in, but its standard error isn't one (is it redirected \
+             to a file?)"
+        );
+    }
 
-    // Restore terminal
-    ratatui::restore();
-    terminal.show_cursor()?;
+    Ok(())
+}
 
-    // Now that we've restored the terminal, return a Result::Err returned by `run` up.
-    res?;
+/// Replaces stdin (the page `man` piped to us) with the terminal that stderr is connected to, so
+/// the TUI can read keys from it.
+///
+/// Fails cleanly (rather than partway through setting up the TUI) if stderr isn't a terminal, e.g.
+/// because it was redirected to a file.
+fn take_terminal_input() -> Result<()> {
+    require_terminal(libc::STDERR_FILENO)?;
+
+    // SAFETY: Simple dup2 call made with two valid fds, with its failure checked below
+    if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDIN_FILENO) } < 0 {
+        return Err(io::Error::last_os_error())
+            .context("libc::dup2 call (to put the terminal's fd over stdin's fd) failed");
+    }
 
-    // Successful exit
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, os::fd::AsRawFd};
+
+    use super::*;
+
+    #[test]
+    fn dumps() {
+        let mut out = Vec::new();
+        dump("", &mut out).unwrap();
+        assert!(out.is_empty());
+
+        dump(
+            "FOO(1)\n\nNAME\n    foo - do \x1b[1mthings\x1b[0m\n",
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "FOO(1)\n\nNAME\n    foo - do things\n"
+        );
+    }
+
+    #[test]
+    fn files_arent_terminals() {
+        let file = File::open("/dev/null").unwrap();
+        let e = require_terminal(file.as_raw_fd()).unwrap_err();
+        assert!(e.to_string().contains("needs a terminal"));
+    }
+}
@@ -2,17 +2,65 @@ use std::error::Error;
 use std::ffi::CString;
 use std::fmt::{self, Display, Formatter};
 
+/// Trailing punctuation (e.g. from "see foo(5).") that is ignored after a man reference
+pub(crate) const TRAILING_PUNCTUATION: [char; 6] = ['.', ',', ';', ':', '!', '?'];
+
 pub(crate) struct ManPageInfo<'a> {
     name: &'a str,
     section_number: &'a str,
 }
 
 #[derive(Debug)]
-pub(crate) struct StringNotManRefError;
+pub(crate) struct StringNotManRefError {
+    input: String,
+    reason: NotManRefReason,
+}
+
+/// Why a string failed to parse as a [`ManPageInfo`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum NotManRefReason {
+    ContainsPathSeparator,
+    ContainsNul,
+    NoOpenParen,
+    NoCloseParen,
+    TrailingCharacters,
+    EmptyName,
+    InvalidName,
+    InvalidSection,
+}
+
+impl StringNotManRefError {
+    fn new(input: &str, reason: NotManRefReason) -> Self {
+        Self {
+            input: input.to_owned(),
+            reason,
+        }
+    }
+}
 
 impl Display for StringNotManRefError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "StringNotManRefError")
+        write!(
+            f,
+            "'{}' is not a valid man reference: {}",
+            self.input.escape_debug(),
+            self.reason
+        )
+    }
+}
+
+impl Display for NotManRefReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ContainsPathSeparator => "contains path separator",
+            Self::ContainsNul => "contains NUL character",
+            Self::NoOpenParen => "no opening parenthesis",
+            Self::NoCloseParen => "no closing parenthesis",
+            Self::TrailingCharacters => "unexpected characters after closing parenthesis",
+            Self::EmptyName => "no name before the section",
+            Self::InvalidName => "name contains characters not allowed in man page names",
+            Self::InvalidSection => "section is not a digit followed by alphanumerics",
+        })
     }
 }
 
@@ -21,41 +69,259 @@ impl Error for StringNotManRefError {}
 impl<'a> TryFrom<&'a str> for ManPageInfo<'a> {
     type Error = StringNotManRefError;
 
-    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+    fn try_from(input: &'a str) -> Result<Self, Self::Error> {
+        let error = |reason| StringNotManRefError::new(input, reason);
+
+        // Strip surrounding whitespace (e.g. picked up from a wrapped line), and trailing
+        // punctuation that can't be part of a reference
+        let value = input.trim().trim_end_matches(TRAILING_PUNCTUATION);
+
         // Disallow path separator and U+0000
-        if value.chars().any(|c| c == '\x00' || c == '/') {
-            return Err(StringNotManRefError);
-        }
-
-        if let Some(open_paren_index) = value.find('(') {
-            let next_char = &value[open_paren_index..]
-                .chars()
-                .nth(1)
-                .ok_or(StringNotManRefError)?;
-            next_char
-                .is_ascii_digit()
-                .then(|| {
-                    Ok(ManPageInfo {
-                        name: &value[..open_paren_index],
-                        section_number: &value[(open_paren_index + 1)..(open_paren_index + 2)], // This subslicing is safe since we know
-                                                                                                // next_char was an ASCII digit
-                    })
-                })
-                .ok_or(StringNotManRefError)?
-        } else {
-            Err(StringNotManRefError)
+        if value.contains('/') {
+            return Err(error(NotManRefReason::ContainsPathSeparator));
+        }
+        if value.contains('\x00') {
+            return Err(error(NotManRefReason::ContainsNul));
+        }
+
+        let open_paren_index = value
+            .find('(')
+            .ok_or_else(|| error(NotManRefReason::NoOpenParen))?;
+        let close_paren_index = value[open_paren_index..]
+            .find(')')
+            .map(|index| open_paren_index + index)
+            .ok_or_else(|| error(NotManRefReason::NoCloseParen))?;
+
+        // Nothing may follow the closing paren (this rejects things like `signal(7)-style`)
+        if close_paren_index != value.len() - 1 {
+            return Err(error(NotManRefReason::TrailingCharacters));
         }
+
+        let name = value[..open_paren_index].trim();
+        validate_name(name).map_err(error)?;
+
+        let section_number = &value[(open_paren_index + 1)..close_paren_index];
+        validate_section(section_number).map_err(error)?;
+
+        Ok(ManPageInfo {
+            name,
+            section_number,
+        })
     }
 }
 
 impl<'a> Display for ManPageInfo<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {}", self.section_number, self.name)
+        if self.section_number.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}({})", self.name, self.section_number)
+        }
     }
 }
 
 impl<'a> ManPageInfo<'a> {
-    pub(crate) fn as_args(&self) -> anyhow::Result<(CString, CString)> {
-        Ok((CString::new(self.section_number)?, CString::new(self.name)?))
+    /// Creates a [`ManPageInfo`] for the page `name` in section `section_number`. An empty
+    /// `section_number` leaves picking the section up to `man`.
+    pub(crate) fn new(
+        name: &'a str,
+        section_number: &'a str,
+    ) -> Result<Self, StringNotManRefError> {
+        let error =
+            |reason| StringNotManRefError::new(&format!("{name}({section_number})"), reason);
+
+        validate_name(name).map_err(error)?;
+        if !section_number.is_empty() {
+            validate_section(section_number).map_err(error)?;
+        }
+
+        Ok(ManPageInfo {
+            name,
+            section_number,
+        })
+    }
+
+    /// Returns the arguments to pass to `man` to open this page (the section, if there is one,
+    /// followed by the name).
+    pub(crate) fn as_args(&self) -> anyhow::Result<Vec<CString>> {
+        let mut args = Vec::with_capacity(2);
+        if !self.section_number.is_empty() {
+            args.push(CString::new(self.section_number)?);
+        }
+        args.push(CString::new(self.name)?);
+
+        Ok(args)
+    }
+
+    /// Returns the `man` command line that opens this page (e.g. `man 2 open`), in the same order
+    /// as [`ManPageInfo::as_args`].
+    pub(crate) fn man_command(&self) -> String {
+        if self.section_number.is_empty() {
+            format!("man {}", self.name)
+        } else {
+            format!("man {} {}", self.section_number, self.name)
+        }
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), NotManRefReason> {
+    if name.contains('/') {
+        Err(NotManRefReason::ContainsPathSeparator)
+    } else if name.contains('\x00') {
+        Err(NotManRefReason::ContainsNul)
+    } else if name.trim().is_empty() {
+        // e.g. a stray `(2)`, which `man` would be asked to open with an empty name
+        Err(NotManRefReason::EmptyName)
+    } else if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '.' | '_' | '-' | '+'))
+    {
+        Err(NotManRefReason::InvalidName)
+    } else {
+        Ok(())
+    }
+}
+
+/// Sections are a digit, optionally followed by more alphanumerics (e.g. `3p`, `1ssl`, `3tiff`)
+pub(crate) fn validate_section(section_number: &str) -> Result<(), NotManRefReason> {
+    if section_number.starts_with(|c: char| c.is_ascii_digit())
+        && section_number.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        Ok(())
+    } else {
+        Err(NotManRefReason::InvalidSection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `input`, returning its name and section.
+    fn parse(input: &str) -> (&str, &str) {
+        let info = ManPageInfo::try_from(input).unwrap();
+        (info.name, info.section_number)
+    }
+
+    /// Parses `input`, returning why it isn't a reference.
+    fn reject(input: &str) -> NotManRefReason {
+        match ManPageInfo::try_from(input) {
+            Ok(info) => panic!("{input:?} parsed as {info}"),
+            Err(e) => e.reason,
+        }
+    }
+
+    #[test]
+    fn sections() {
+        assert_eq!(parse("open(2)"), ("open", "2"));
+        assert_eq!(parse("foo(3p)"), ("foo", "3p"));
+        assert_eq!(parse("bar(1ssl)"), ("bar", "1ssl"));
+        assert_eq!(parse("baz(3tiff)"), ("baz", "3tiff"));
+
+        let info = ManPageInfo::try_from("bar(1ssl)").unwrap();
+        assert_eq!(
+            info.as_args().unwrap(),
+            [c"1ssl".to_owned(), c"bar".to_owned()]
+        );
+    }
+
+    #[test]
+    fn invalid_sections() {
+        assert_eq!(reject("foo(p3)"), NotManRefReason::InvalidSection);
+        assert_eq!(reject("foo()"), NotManRefReason::InvalidSection);
+        assert_eq!(reject("foo(3 p)"), NotManRefReason::InvalidSection);
+        assert_eq!(reject("foo(3-p)"), NotManRefReason::InvalidSection);
+    }
+
+    #[test]
+    fn names_with_dots_and_hyphens() {
+        assert_eq!(parse("resolv.conf(5)"), ("resolv.conf", "5"));
+        assert_eq!(parse("git-commit(1)"), ("git-commit", "1"));
+        assert_eq!(parse("systemd.service(5)"), ("systemd.service", "5"));
+
+        let info = ManPageInfo::try_from("systemd.service(5)").unwrap();
+        assert_eq!(
+            info.as_args().unwrap(),
+            [c"5".to_owned(), c"systemd.service".to_owned()]
+        );
+        assert_eq!(info.man_command(), "man 5 systemd.service");
+    }
+
+    #[test]
+    fn trailing_punctuation() {
+        assert_eq!(parse("foo(5)."), ("foo", "5"));
+        assert_eq!(parse("foo(5),"), ("foo", "5"));
+        assert_eq!(parse("foo(5);"), ("foo", "5"));
+        assert_eq!(parse("resolv.conf(5)."), ("resolv.conf", "5"));
+    }
+
+    #[test]
+    fn false_positives() {
+        assert_eq!(reject("errno(3)))"), NotManRefReason::TrailingCharacters);
+        assert_eq!(
+            reject("signal(7)-style"),
+            NotManRefReason::TrailingCharacters
+        );
+        assert_eq!(reject("foo(1)bar"), NotManRefReason::TrailingCharacters);
+        assert_eq!(reject("printf(\"%d\")"), NotManRefReason::InvalidSection);
+        assert_eq!(reject("x*y(2)"), NotManRefReason::InvalidName);
+        assert_eq!(reject("open"), NotManRefReason::NoOpenParen);
+        assert_eq!(reject("open(2"), NotManRefReason::NoCloseParen);
+        assert_eq!(
+            reject("/usr/bin(1)"),
+            NotManRefReason::ContainsPathSeparator
+        );
+        assert_eq!(reject("foo\0(1)"), NotManRefReason::ContainsNul);
+    }
+
+    #[test]
+    fn error_messages() {
+        let error = ManPageInfo::try_from("foo/bar").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "'foo/bar' is not a valid man reference: contains path separator"
+        );
+    }
+
+    #[test]
+    fn without_section() {
+        let info = ManPageInfo::new("ls", "").unwrap();
+        assert_eq!(info.to_string(), "ls");
+        assert_eq!(info.as_args().unwrap(), [c"ls".to_owned()]);
+        assert_eq!(info.man_command(), "man ls");
+
+        assert!(ManPageInfo::new("ls", "x").is_err());
+        assert!(ManPageInfo::new("l/s", "1").is_err());
+    }
+
+    #[test]
+    fn displays_as_references() {
+        let info = ManPageInfo::try_from("resolv.conf(5).").unwrap();
+        assert_eq!(info.to_string(), "resolv.conf(5)");
+        assert_eq!(parse(&info.to_string()), ("resolv.conf", "5"));
+    }
+
+    #[test]
+    fn empty_names() {
+        assert_eq!(reject("(2)"), NotManRefReason::EmptyName);
+        assert_eq!(reject("(2),"), NotManRefReason::EmptyName);
+        assert_eq!(reject("   (2)"), NotManRefReason::EmptyName);
+        assert!(matches!(
+            ManPageInfo::new("", "1").err().map(|e| e.reason),
+            Some(NotManRefReason::EmptyName)
+        ));
+        assert_eq!(
+            ManPageInfo::try_from("(2)").err().unwrap().to_string(),
+            "'(2)' is not a valid man reference: no name before the section"
+        );
+    }
+
+    #[test]
+    fn surrounding_whitespace() {
+        assert_eq!(parse(" read(2)"), ("read", "2"));
+        assert_eq!(parse("read(2) "), ("read", "2"));
+        assert_eq!(parse("\tread(2)\n"), ("read", "2"));
+        assert_eq!(parse("read (2)"), ("read", "2"));
+        assert_eq!(parse(" read(2). "), ("read", "2"));
     }
 }
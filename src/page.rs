@@ -0,0 +1,141 @@
+use std::{cell::OnceCell, mem, ops::Range};
+
+use anyhow::Result;
+use ratatui::text::{Line, Text};
+
+use crate::text_handling;
+
+/// A man page, both as given (with its ANSI formatting) and as laid out for display.
+///
+/// The styled [`Text`] that's drawn and the plain lines that are searched and hit-tested (e.g. for
+/// clicks) are always laid out together from the same content and wrap width, so line `i` of one is
+/// line `i` of the other.
+#[derive(Debug, Default)]
+pub(crate) struct Page {
+    /// The page as `man` gave it, with its ANSI formatting
+    content: String,
+    /// Width the page is soft-wrapped to, if it's wrapped at all
+    wrap_width: Option<usize>,
+    /// Whether colors are removed from [`Page::text`]
+    no_color: bool,
+    text: Text<'static>,
+    /// Plain text of each line of [`Page::text`]
+    lines: Vec<String>,
+    /// Byte offset each of [`Page::lines`] starts at in the line of the page it was wrapped from
+    /// (see [`text_handling::format_content`])
+    line_starts: Vec<usize>,
+    /// Byte ranges of the man references in each of [`Page::lines`], found the first time they're
+    /// asked for (see [`Page::man_refs`])
+    man_refs: Vec<OnceCell<Vec<Range<usize>>>>,
+    /// [`Page::lines`] joined back together
+    plain_text: String,
+    /// Width of the widest line, in columns
+    max_line_width: u16,
+}
+
+impl Page {
+    /// Lays out `content` (wrapped to `wrap_width`, if given), without colors if `no_color` is set.
+    pub(crate) fn new(content: String, wrap_width: Option<usize>, no_color: bool) -> Result<Self> {
+        let mut page = Self {
+            content,
+            wrap_width,
+            no_color,
+            ..Default::default()
+        };
+        page.lay_out()?;
+
+        Ok(page)
+    }
+
+    /// Lays out `content` in place of the current content (at the same width), returning the
+    /// current content.
+    pub(crate) fn replace_content(&mut self, content: String) -> Result<String> {
+        let previous = mem::replace(&mut self.content, content);
+        self.lay_out()?;
+
+        Ok(previous)
+    }
+
+    /// Lays the page out again, without colors if `no_color` is set.
+    pub(crate) fn set_no_color(&mut self, no_color: bool) -> Result<()> {
+        self.no_color = no_color;
+        self.lay_out()
+    }
+
+    /// Lays the page out again, soft-wrapped to `wrap_width` (or not wrapped, if it's [`None`]).
+    pub(crate) fn rewrap(&mut self, wrap_width: Option<usize>) -> Result<()> {
+        self.wrap_width = wrap_width;
+        self.lay_out()
+    }
+
+    /// Re-parses [`Page::content`] (wrapped to [`Page::wrap_width`]), refreshing everything
+    /// derived from it.
+    fn lay_out(&mut self) -> Result<()> {
+        (self.text, self.line_starts) =
+            text_handling::format_content(&self.content, self.wrap_width)?;
+        if self.no_color {
+            text_handling::remove_colors(&mut self.text);
+        }
+        self.lines = text_handling::plain_lines(&self.text);
+        self.man_refs = vec![OnceCell::new(); self.lines.len()];
+        self.plain_text = self.lines.join("\n");
+        self.max_line_width = self
+            .text
+            .lines
+            .iter()
+            .map(Line::width)
+            .max()
+            .unwrap_or_default()
+            .try_into()
+            .unwrap_or(u16::MAX);
+
+        Ok(())
+    }
+
+    /// Returns the page as `man` gave it, with its ANSI formatting.
+    pub(crate) fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Returns the width the page is soft-wrapped to, if it's wrapped.
+    pub(crate) fn wrap_width(&self) -> Option<usize> {
+        self.wrap_width
+    }
+
+    /// Returns the laid out page, as drawn.
+    pub(crate) fn text(&self) -> &Text<'static> {
+        &self.text
+    }
+
+    /// Returns the plain text of each line of [`Page::text`].
+    pub(crate) fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Returns the byte offset each of [`Page::lines`] starts at in the line of the page it was
+    /// wrapped from.
+    pub(crate) fn line_starts(&self) -> &[usize] {
+        &self.line_starts
+    }
+
+    /// Returns the byte ranges of the man references in line `line_index` of [`Page::lines`] (see
+    /// [`text_handling::find_man_refs`]).
+    ///
+    /// They're found the first time they're asked for and kept until the page is laid out again,
+    /// so drawing them, hovering over them, and labeling them don't each search the lines on
+    /// screen again every frame.
+    pub(crate) fn man_refs(&self, line_index: usize) -> &[Range<usize>] {
+        self.man_refs[line_index]
+            .get_or_init(|| text_handling::find_man_refs(&self.lines[line_index]))
+    }
+
+    /// Returns the plain text of the laid out page.
+    pub(crate) fn plain_text(&self) -> &str {
+        &self.plain_text
+    }
+
+    /// Returns the width of the widest line, in columns.
+    pub(crate) fn max_line_width(&self) -> u16 {
+        self.max_line_width
+    }
+}
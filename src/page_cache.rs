@@ -0,0 +1,114 @@
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsStr,
+    fs,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::{ManPageInfo, app};
+
+/// Exit status `man` (man-db) uses when the requested page doesn't exist
+const MAN_NOT_FOUND_STATUS: i32 = 16;
+
+/// Pages cached on disk longer ago than this are formatted again, in case they've been updated
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Cache of formatted man pages (as `man` would pipe them to its pager), keyed by the page's
+/// reference (e.g. `printf(3)`).
+///
+/// Pages are kept in memory, and on disk under `$XDG_CACHE_HOME/linkman` (or `~/.cache/linkman`)
+/// so other `linkman`s (e.g. nested ones, which are separate processes) can use them too. Since
+/// pages are formatted to `MANWIDTH` columns, each width gets its own directory there. Failing to
+/// read or write the disk cache isn't an error; it just means running `man` again.
+#[derive(Debug, Default)]
+pub(crate) struct PageCache(HashMap<String, String>);
+
+impl PageCache {
+    /// Returns the formatted page described by `info`, running `man` to format it if it isn't
+    /// cached.
+    pub(crate) fn get(&mut self, info: &ManPageInfo) -> Result<String> {
+        let key = info.to_string();
+        if let Some(content) = self.0.get(&key) {
+            return Ok(content.clone());
+        }
+
+        match cache_path(&key).as_deref().and_then(read_fresh) {
+            Some(content) => {
+                self.0.insert(key, content.clone());
+                Ok(content)
+            }
+            None => self.reload(info),
+        }
+    }
+
+    /// Runs `man` to format the page described by `info` even if it's cached (e.g. because it's
+    /// been changed since), replacing the cached page.
+    pub(crate) fn reload(&mut self, info: &ManPageInfo) -> Result<String> {
+        let key = info.to_string();
+        let content = run_man(info)?;
+        if let Some(path) = cache_path(&key)
+            && let Some(dir) = path.parent()
+        {
+            drop(fs::create_dir_all(dir).and_then(|()| fs::write(&path, &content)));
+        }
+
+        self.0.insert(key, content.clone());
+        Ok(content)
+    }
+}
+
+/// Returns where the page with reference `key` is cached on disk, if there's anywhere to cache it.
+fn cache_path(key: &str) -> Option<PathBuf> {
+    let cache_dir = env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    let width = env::var("MANWIDTH").unwrap_or_else(|_| String::from("default"));
+
+    // `key` is a validated reference, so it can't contain a path separator
+    Some(cache_dir.join("linkman").join(width).join(key))
+}
+
+/// Reads the cached page at `path`, unless it's older than [`MAX_AGE`].
+fn read_fresh(path: &Path) -> Option<String> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    if SystemTime::now().duration_since(modified).ok()? > MAX_AGE {
+        return None;
+    }
+
+    fs::read_to_string(path).ok()
+}
+
+/// Runs `man` to format the page described by `info`, capturing what it would have piped to its
+/// pager.
+fn run_man(info: &ManPageInfo) -> Result<String> {
+    let args = info.as_args()?;
+    let output = Command::new(app::man_program())
+        // Format for the terminal (with bold, underlining, etc.) even though it's not a terminal
+        // that `man` is writing to
+        .env("MAN_KEEP_FORMATTING", "1")
+        .arg("-P")
+        .arg("cat")
+        .args(args.iter().map(|arg| OsStr::from_bytes(arg.to_bytes())))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run man to open {info}"))?;
+
+    match output.status.code() {
+        Some(0) => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+        Some(MAN_NOT_FOUND_STATUS) => Err(anyhow!("No manual entry for {info}")),
+        Some(code) => Err(anyhow!(
+            "man exited with status {code} while opening {info}"
+        )),
+        None => Err(anyhow!("man was killed by a signal while opening {info}")),
+    }
+}
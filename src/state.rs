@@ -0,0 +1,75 @@
+use std::{env, fs, io, path::PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// Preferences toggled while `linkman` runs, remembered between runs in
+/// `$XDG_STATE_HOME/linkman/state.toml` (or `~/.local/state/linkman/state.toml`).
+///
+/// Unlike the [`crate::config::Config`], which the user writes, this is written by `linkman`
+/// itself. Remembering is best-effort: a state file that can't be read is treated as the defaults,
+/// and one that can't be written just isn't.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct State {
+    /// Whether searches ignore case, if that was chosen (with Alt+C) rather than left to smart-case
+    pub(crate) search_ignore_case: Option<bool>,
+    /// Whether lines are soft-wrapped to the terminal's width
+    pub(crate) wrap: bool,
+    /// Whether the mouse is captured to click links (rather than left to the terminal to select
+    /// text with)
+    pub(crate) mouse_capture: bool,
+    /// Whether pages are shown in color
+    pub(crate) color: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            search_ignore_case: None,
+            wrap: true,
+            mouse_capture: true,
+            color: true,
+        }
+    }
+}
+
+impl State {
+    /// Loads the state file, or returns the default state if there isn't one.
+    pub(crate) fn load() -> Result<Self> {
+        let Some(path) = state_path() else {
+            return Ok(Self::default());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)
+                .with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Writes the state file, creating its directory if needed.
+    pub(crate) fn save(&self) -> Result<()> {
+        let path = state_path().ok_or_else(|| anyhow!("Nowhere to save the state file"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+
+        fs::write(&path, toml::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Returns where the state file should be, if there's anywhere it could be.
+fn state_path() -> Option<PathBuf> {
+    let state_dir = env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+
+    Some(state_dir.join("linkman").join("state.toml"))
+}
@@ -0,0 +1,130 @@
+use std::{
+    io,
+    ops::{Deref, DerefMut},
+    panic,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::Result;
+use ratatui::{
+    DefaultTerminal, Terminal,
+    crossterm::{
+        cursor,
+        event::{DisableMouseCapture, EnableMouseCapture},
+        execute,
+        style::Print,
+        terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    prelude::CrosstermBackend,
+};
+
+/// Whether the terminal has been restored, so that a panic (whose hook restores the terminal before
+/// printing its message) followed by unwinding through [`TerminalGuard`]'s `drop` only restores it
+/// once.
+static RESTORED: AtomicBool = AtomicBool::new(false);
+/// Whether [`TerminalGuard::new`] switched to the alternate screen (so restoring it should switch
+/// back)
+static ALT_SCREEN: AtomicBool = AtomicBool::new(true);
+
+/// Sets up the terminal for the TUI (raw mode, the alternate screen, and mouse capture), and
+/// restores it when dropped, whether that's on a normal exit, an error, or a panic.
+pub(crate) struct TerminalGuard(DefaultTerminal);
+
+impl TerminalGuard {
+    /// Sets up the terminal, using the alternate screen if `alt_screen` is set. Otherwise the TUI
+    /// is drawn over the main screen, and its last frame is left there (and in the terminal's
+    /// scrollback) on exit.
+    pub(crate) fn new(alt_screen: bool) -> Result<Self> {
+        // Restore the terminal before the default hook prints the panic message, so the message
+        // isn't lost on the alternate screen
+        let old_panic_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |hook_info| {
+            restore();
+            old_panic_hook(hook_info);
+        }));
+
+        // Construct the guard before changing anything, so a failure partway through still restores
+        // whatever was set up
+        RESTORED.store(false, Ordering::SeqCst);
+        ALT_SCREEN.store(alt_screen, Ordering::SeqCst);
+        let mut guard = Self(Terminal::new(CrosstermBackend::new(io::stdout()))?);
+
+        reacquire()?;
+        execute!(
+            io::stdout(),
+            EnableMouseCapture, // Starting in MouseMode::LinkClicking (this also reports pointer motion, for hover highlighting)
+        )?;
+        guard.0.clear()?;
+
+        Ok(guard)
+    }
+}
+
+impl Deref for TerminalGuard {
+    type Target = DefaultTerminal;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for TerminalGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Puts the terminal back into raw mode (and onto the alternate screen, if [`TerminalGuard::new`]
+/// was asked to use it), e.g. after a nested `linkman` that shared the terminal restored it on
+/// exit.
+pub(crate) fn reacquire() -> Result<()> {
+    terminal::enable_raw_mode()?;
+    if ALT_SCREEN.load(Ordering::SeqCst) {
+        execute!(io::stdout(), EnterAlternateScreen)?;
+    }
+
+    Ok(())
+}
+
+/// Hands the terminal back (much like on exit) so another program can use it, until [`reacquire`]
+/// is called.
+pub(crate) fn suspend() -> Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(io::stdout(), DisableMouseCapture, cursor::Show)?;
+    if ALT_SCREEN.load(Ordering::SeqCst) {
+        execute!(io::stdout(), LeaveAlternateScreen)?;
+    }
+
+    Ok(())
+}
+
+/// Undoes [`TerminalGuard::new`]'s terminal setup (if it hasn't been already), ignoring errors
+/// since there's nothing better to do with them at this point.
+fn restore() {
+    if RESTORED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    // Disabling raw mode first is important, as it has more side effects than leaving the
+    // alternate screen
+    drop(terminal::disable_raw_mode());
+    drop(execute!(io::stdout(), DisableMouseCapture, cursor::Show));
+
+    if ALT_SCREEN.load(Ordering::SeqCst) {
+        drop(execute!(io::stdout(), LeaveAlternateScreen));
+    } else {
+        // Leave the last frame where it is, and start the shell's prompt below it
+        let rows = terminal::size().map_or(1, |(_, rows)| rows);
+        drop(execute!(
+            io::stdout(),
+            cursor::MoveTo(0, rows.saturating_sub(1)),
+            Print("\r\n")
+        ));
+    }
+}
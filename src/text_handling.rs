@@ -1,104 +1,986 @@
-use anyhow::{Result, anyhow};
+use std::{borrow::Cow, iter, ops::Range};
+
+use ansi_to_tui::IntoText;
+use anyhow::{Result, bail};
+use ratatui::{
+    style::Style,
+    text::{Line, Span, Text},
+};
 use strip_ansi_escapes::strip_str;
 
+use crate::{ManPageInfo, man_page_info::TRAILING_PUNCTUATION};
+
+/// A run of text found in one of a page's lines (e.g. by [`word_span_at_position`])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LineSpan<'a> {
+    /// The text itself
+    pub(crate) text: &'a str,
+    /// Index of the line the text is in
+    pub(crate) line_index: usize,
+    /// Byte range of the text within its line
+    pub(crate) bytes: Range<usize>,
+}
+
 /// Returns a reference ([`&str`]) the word at the given position in the given lines of text.
-///
-/// # NOTE
-/// This function is `unsafe` because it can only be called from a single-threaded context.
-/// This is due to the fact that it uses a `static mut` map to cache computations.
-pub unsafe fn word_at_position(
+pub(crate) fn word_at_position(
     lines: &[String],
     scroll: usize,
     row: usize,
-    mut col: usize,
+    col: usize,
 ) -> Option<&str> {
-    use unicode_segmentation::UnicodeSegmentation;
+    word_span_at_position(lines, scroll, row, col).map(|span| span.text)
+}
 
-    col = col.checked_sub(1)?;
+/// Returns the word at the given position in the given lines of text, along with where it is.
+/// Parentheses the word is enclosed in (see [`strip_enclosing_parens`]) aren't part of it.
+pub(crate) fn word_span_at_position<'a>(
+    lines: &'a [String],
+    scroll: usize,
+    row: usize,
+    col: usize,
+) -> Option<LineSpan<'a>> {
+    let span = span_at_position(lines, scroll, row, col, word_bounds)?;
+    let line = &lines[span.line_index];
+    let bytes = strip_enclosing_parens(line, span.bytes);
 
-    // Module in place to prevent accidental direct use of `static mut` pointer `LINE_OFFSETS_CACHE`.
-    mod offsets_cache {
-        use std::collections::HashMap;
-        use std::ptr;
-        static mut LINE_OFFSETS_CACHE: *mut HashMap<String, Vec<usize>> = ptr::null_mut();
+    (!bytes.is_empty()).then(|| LineSpan {
+        text: &line[bytes.clone()],
+        line_index: span.line_index,
+        bytes,
+    })
+}
 
-        pub(super) fn get_cache<'a>() -> &'a mut HashMap<String, Vec<usize>> {
-            unsafe {
-                if LINE_OFFSETS_CACHE.is_null() {
-                    LINE_OFFSETS_CACHE = Box::into_raw(Box::new(HashMap::new()));
-                }
-                &mut *LINE_OFFSETS_CACHE
-            }
-        }
+/// Narrows the byte range `word` of `line` to leave out the parentheses it's enclosed in, like the
+/// last `)` of `signal(7))` in "(see signal(7))". Trailing punctuation after them is left out too.
+///
+/// Leading `(`s can't be part of a reference, so they're always left out. A trailing `)` is only
+/// left out if it closes a `(` opened earlier in the line and isn't needed to close one in the
+/// word, so a stray one (like in `errno(3)))`) is kept, and still keeps the word from being a
+/// reference.
+fn strip_enclosing_parens(line: &str, word: Range<usize>) -> Range<usize> {
+    let count = |text: &str, paren| text.chars().filter(|&c| c == paren).count();
+
+    let mut start = word.start;
+    while line[start..word.end].starts_with('(') {
+        start += 1;
+    }
+    let end = word.start
+        + line[word.clone()]
+            .trim_end_matches(TRAILING_PUNCTUATION)
+            .len();
+    if end <= start {
+        return word;
+    }
+
+    let before = &line[..start];
+    let mut unclosed = count(before, '(').saturating_sub(count(before, ')'));
+    let mut end = end;
+    while unclosed > 0
+        && line[start..end].ends_with(')')
+        && count(&line[start..end], ')') > count(&line[start..end], '(')
+    {
+        end -= 1;
+        unclosed -= 1;
     }
 
-    let line = lines.get(row.checked_add(scroll)?.checked_sub(1)?)?;
+    start..end
+}
+
+/// Returns `word` without the parentheses it's enclosed in, or the punctuation after it (see
+/// [`strip_enclosing_parens`]).
+fn without_enclosing_parens(word: &str) -> &str {
+    &word[strip_enclosing_parens(word, 0..word.len())]
+}
+
+/// Returns the man references (e.g. `getaddrinfo(3)`) the text at the given position in the given
+/// lines of text could be, most likely first. This is empty if there's no reference there.
+///
+/// References wrapped onto two lines (e.g. in a long SEE ALSO list), whether between the name and
+/// the section or partway through a hyphenated name, are joined back together.
+///
+/// A reference to a subcommand written after its tool, like `git commit(1)`, is most likely to the
+/// tool's page for it (`git-commit(1)`), so that's given first, followed by the reference as
+/// written (`commit(1)`) in case there's no such page.
+pub(crate) fn man_refs_at_position(
+    lines: &[String],
+    scroll: usize,
+    row: usize,
+    col: usize,
+) -> Vec<String> {
+    // Look at the whole whitespace-delimited token first, since `word_at_position` stops at a `(`
+    // starting the line
+    let Some(LineSpan {
+        text: token,
+        line_index,
+        bytes: Range { start, end },
+    }) = span_at_position(lines, scroll, row, col, token_bounds)
+    else {
+        return Vec::new();
+    };
+    let line = &lines[line_index];
+
+    // The rest of the reference may start the next line, or the start of it may end the previous
+    // line. Either way, the token at the line break must show that the word carries on across it.
+    let next = line[end..]
+        .trim()
+        .is_empty()
+        .then(|| lines.get(line_index + 1)?.split_whitespace().next())
+        .flatten()
+        .map(|next| (token, next));
+    let previous = line[..start]
+        .trim()
+        .is_empty()
+        .then(|| {
+            lines
+                .get(line_index.checked_sub(1)?)?
+                .split_whitespace()
+                .next_back()
+        })
+        .flatten()
+        .map(|previous| (previous, token));
+    let joined = next
+        .into_iter()
+        .chain(previous)
+        .filter(|(head, tail)| head.ends_with(['-', HYPHENATION_HYPHEN]) || tail.starts_with('('))
+        .map(|(head, tail)| join_wrapped(head, tail))
+        // Leave off what surrounds the reference, like the comma after it in a SEE ALSO list
+        .map(|joined| without_enclosing_parens(&joined).to_owned())
+        .find(|joined| ManPageInfo::try_from(joined.as_str()).is_ok());
+    if let Some(joined) = joined {
+        return vec![joined];
+    }
+
+    let Some(word) = word_at_position(lines, scroll, row, col)
+        .filter(|word| ManPageInfo::try_from(*word).is_ok())
+    else {
+        return Vec::new();
+    };
+
+    // The tool may also have been left at the end of the previous line
+    let tool = match line[..start].split_whitespace().next_back() {
+        Some(tool) => Some(tool),
+        None => lines
+            .get(line_index.wrapping_sub(1))
+            .and_then(|previous| previous.split_whitespace().next_back()),
+    };
+    tool.filter(|tool| is_tool_name(tool))
+        .map(|tool| format!("{tool}-{word}"))
+        .filter(|subcommand| ManPageInfo::try_from(subcommand.as_str()).is_ok())
+        .into_iter()
+        .chain([word.to_owned()])
+        .collect()
+}
+
+/// Returns whether `word` could be the name of a tool with subcommands (e.g. `git` or
+/// `systemctl`), rather than ordinary text or another reference.
+fn is_tool_name(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_lowercase())
+        && word
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_'))
+}
+
+/// Hyphen `groff` inserts when it hyphenates a word at the end of a line (unlike a `-` that's part
+/// of the word, e.g. in `systemd-analyze`)
+const HYPHENATION_HYPHEN: char = '\u{2010}';
+
+/// Joins `head`, the end of one line, and `tail`, the start of the next, back into the word they
+/// were wrapped from.
+fn join_wrapped(head: &str, tail: &str) -> String {
+    format!(
+        "{}{tail}",
+        head.strip_suffix(HYPHENATION_HYPHEN).unwrap_or(head)
+    )
+}
+
+/// Returns the URL to open for the web or email address at the given position in the given
+/// lines of text, if there is one there. Bare email addresses get a `mailto:` prefix.
+pub(crate) fn external_link_at_position(
+    lines: &[String],
+    scroll: usize,
+    row: usize,
+    col: usize,
+) -> Option<String> {
+    let word = span_at_position(lines, scroll, row, col, token_bounds)?
+        .text
+        // Addresses are often wrapped in brackets or quotes, or followed by punctuation
+        .trim_start_matches(['<', '(', '[', '"', '\''])
+        .trim_end_matches(|c| {
+            matches!(c, '>' | ')' | ']' | '"' | '\'') || TRAILING_PUNCTUATION.contains(&c)
+        });
+
+    if ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| word.starts_with(scheme) && word.len() > scheme.len())
+    {
+        Some(word.to_owned())
+    } else if is_email_address(word) {
+        Some(format!("mailto:{word}"))
+    } else {
+        None
+    }
+}
+
+/// Returns whether `word` looks like an email address (`local@domain.tld`).
+fn is_email_address(word: &str) -> bool {
+    let Some((local, domain)) = word.split_once('@') else {
+        return false;
+    };
+
+    !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '.' | '_' | '%' | '+' | '-'))
+        && domain.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+        && domain.contains('.')
+}
+
+/// Finds the range of grapheme clusters making up a span of text (see [`word_bounds`])
+type BoundsFn = fn(&[&str], usize) -> Option<(usize, usize)>;
+
+/// Returns the run of text at the given position in the given lines of text, as found by `bounds`
+/// (e.g. [`word_bounds`]).
+fn span_at_position<'a>(
+    lines: &'a [String],
+    scroll: usize,
+    row: usize,
+    mut col: usize,
+    bounds: BoundsFn,
+) -> Option<LineSpan<'a>> {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    col = col.checked_sub(1)?;
+
+    let line_index = row.checked_add(scroll)?.checked_sub(1)?;
+    let line = lines.get(line_index)?;
 
     // Group line by Unicode extended grapheme clusters, as recommended by [UAX #29](https://www.unicode.org/reports/tr29/#Grapheme_Cluster_Boundaries)
     let graphemes: Vec<&str> = UnicodeSegmentation::graphemes(line.as_str(), true).collect();
 
-    if col >= graphemes.len() {
-        // Column is out of bounds
-        return None;
-    }
+    // Map the (display) column to the grapheme cluster drawn there
+    let col = grapheme_at_column(&graphemes, col)?;
+    let (start, end) = bounds(&graphemes, col)?;
+
+    // Working out the byte offsets costs far less than splitting the line into graphemes did, so
+    // they aren't worth caching between calls
+    let offset = |index: usize| -> usize { graphemes[..index].iter().map(|g| g.len()).sum() };
+    let bytes = offset(start)..offset(end);
+
+    Some(LineSpan {
+        text: &line[bytes.clone()],
+        line_index,
+        bytes,
+    })
+}
+
+/// Returns the range of grapheme clusters (as `(start, end)` indices into `graphemes`, with `end`
+/// exclusive) making up the word containing the grapheme at index `col`, or [`None`] if that
+/// grapheme is whitespace (or a `/` with no word before it).
+///
+/// Words are split at whitespace and `/` (so each path component is its own word). A word also
+/// starts after a `(` or `)`, but doesn't end at one, so clicking anywhere in the name of a
+/// reference like `ls(1)` gets the whole reference.
+pub(crate) fn word_bounds(graphemes: &[&str], col: usize) -> Option<(usize, usize)> {
+    span_bounds(
+        graphemes,
+        col,
+        |c| char::is_whitespace(c) || c == '/' || c == '(' || c == ')',
+        |c| char::is_whitespace(c) || c == '/',
+    )
+}
+
+/// Like [`word_bounds`], but only splitting at whitespace.
+fn token_bounds(graphemes: &[&str], col: usize) -> Option<(usize, usize)> {
+    span_bounds(graphemes, col, char::is_whitespace, char::is_whitespace)
+}
 
-    // If the grapheme is whitespace, return None
-    if graphemes[col].chars().all(char::is_whitespace) {
+/// Returns the range of grapheme clusters around the one at index `col` in `graphemes`, extending
+/// backward until a grapheme made up of `starts_after` characters and forward until one made up
+/// of `ends_at` characters. Returns [`None`] if the grapheme at `col` is whitespace (or past the
+/// end).
+fn span_bounds(
+    graphemes: &[&str],
+    col: usize,
+    starts_after: impl Fn(char) -> bool,
+    ends_at: impl Fn(char) -> bool,
+) -> Option<(usize, usize)> {
+    if graphemes.get(col)?.chars().all(char::is_whitespace) {
         return None;
     }
 
-    // Walk backward to find the start of the word
+    // Walk backward to find the start of the span
     let mut start = col;
-    while start > 0
-        && !graphemes[start - 1]
-            .chars()
-            .all(|c| char::is_whitespace(c) || c == '/' || c == '(' || c == ')')
-    {
+    while start > 0 && !graphemes[start - 1].chars().all(&starts_after) {
         start -= 1;
     }
 
-    // Walk forward to find the end of the word
+    // Walk forward to find the end of the span
     let mut end = col;
-    while end < graphemes.len()
-        && !graphemes[end]
-            .chars()
-            .all(|c| char::is_whitespace(c) || c == '/')
-    {
+    while end < graphemes.len() && !graphemes[end].chars().all(&ends_at) {
         end += 1;
     }
 
-    // TODO: Benchmark this code with vs. without the cache and use whichever version was faster
-    let offsets_cache = offsets_cache::get_cache();
-    if let Some(offsets) = offsets_cache.get(line.as_str()) {
-        // Cached offsets were present. Use those to compute returned string slice.
-        let start_byte = offsets[start];
-        let end_byte = offsets[end];
+    // Clicking a separator on its own (e.g. the first `/` of a path) finds nothing
+    (start < end).then_some((start, end))
+}
 
-        Some(&line[start_byte..end_byte])
-    } else {
-        // Compute cached offsets
-        let mut byte_offsets = Vec::with_capacity(graphemes.len() + 1);
-        let mut offset_accum = 0;
-        byte_offsets.push(offset_accum);
-        for grapheme in &graphemes {
-            offset_accum += grapheme.len();
-            byte_offsets.push(offset_accum);
+/// Returns the indices of the lines in `lines` that look like man page section headings (e.g.
+/// `NAME`, `SEE ALSO`): lines starting at column 0 with no lowercase letters.
+pub(crate) fn find_headings(lines: &[String]) -> Vec<usize> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            line.starts_with(|c: char| !c.is_whitespace())
+                && line.chars().any(char::is_alphabetic)
+                && !line.chars().any(char::is_lowercase)
+        })
+        .map(|(line_index, _)| line_index)
+        .collect()
+}
+
+/// Tab stops are every this many columns
+const TAB_WIDTH: usize = 8;
+
+/// Returns the index of the grapheme cluster in `graphemes` that covers display column `col`,
+/// accounting for wide (e.g. CJK) characters and tab stops. Returns [`None`] if `col` is past the
+/// end of the line.
+fn grapheme_at_column(graphemes: &[&str], col: usize) -> Option<usize> {
+    use unicode_width::UnicodeWidthStr;
+
+    let mut grapheme_start = 0;
+    for (index, grapheme) in graphemes.iter().enumerate() {
+        let width = if *grapheme == "\t" {
+            TAB_WIDTH - grapheme_start % TAB_WIDTH
+        } else {
+            grapheme.width()
+        };
+
+        if col < grapheme_start + width {
+            return Some(index);
         }
+        grapheme_start += width;
+    }
 
-        let start_byte = byte_offsets[start];
-        let end_byte = byte_offsets[end];
+    None
+}
 
-        // Update cache
-        offsets_cache.insert(line.clone(), byte_offsets);
+/// Shortens `text` to at most `max_width` columns (if it's any wider) by replacing its middle with
+/// an ellipsis, keeping as much of its start and end as fit.
+pub(crate) fn elide_middle(text: &str, max_width: usize) -> Cow<'_, str> {
+    use unicode_segmentation::UnicodeSegmentation;
+    use unicode_width::UnicodeWidthStr;
 
-        Some(&line[start_byte..end_byte])
+    if text.width() <= max_width {
+        return Cow::Borrowed(text);
     }
+    // Too narrow for even the ellipsis
+    let Some(budget) = max_width.checked_sub(ELLIPSIS.width()) else {
+        return Cow::Borrowed("");
+    };
+
+    // The start gets any odd column, since it's usually what identifies the text
+    let mut head_width = budget.div_ceil(2);
+    let mut tail_width = budget - head_width;
+    let graphemes: Vec<&str> = text.graphemes(true).collect();
+    let head_len = graphemes
+        .iter()
+        .take_while(|grapheme| {
+            let fits = grapheme.width() <= head_width;
+            head_width = head_width.saturating_sub(grapheme.width());
+            fits
+        })
+        .count();
+    let tail_len = graphemes[head_len..]
+        .iter()
+        .rev()
+        .take_while(|grapheme| {
+            let fits = grapheme.width() <= tail_width;
+            tail_width = tail_width.saturating_sub(grapheme.width());
+            fits
+        })
+        .count();
+
+    Cow::Owned(format!(
+        "{}{ELLIPSIS}{}",
+        graphemes[..head_len].concat(),
+        graphemes[graphemes.len() - tail_len..].concat()
+    ))
 }
 
+/// What [`elide_middle`] replaces the middle of text with
+const ELLIPSIS: &str = "…";
+
+/// Returns the identifier of the man page (e.g. `MOUNT(2)`) in `s`, i.e. its first
+/// whitespace-delimited token (ignoring any leading byte order mark).
 pub(crate) fn get_man_string(s: &str) -> Result<String> {
+    let s = s.strip_prefix('\u{feff}').unwrap_or(s).trim_start();
+    if s.is_empty() {
+        bail!("Received an empty (or whitespace-only) man page");
+    }
+
     Ok(strip_str(
-        &s[..s
-            .find(char::is_whitespace)
-            .ok_or_else(|| anyhow!("No whitespace in entire man page"))?],
+        &s[..s.find(char::is_whitespace).unwrap_or(s.len())],
     ))
 }
+
+/// Returns the line index and byte range of every (non-overlapping) occurrence of `query` in
+/// `lines`. An empty `query` matches nothing.
+///
+/// Lines soft-wrapped from the same line of the page (as given by `line_starts`, see
+/// [`format_content`]) are searched as that one line, so a match can start on one of them and
+/// carry on onto the next. The range of such a match is relative to the start of the line it
+/// starts on, so it ends past that line.
+///
+/// If `case_insensitive` is set, both `lines` and `query` are lowercased (by Unicode's rules, to
+/// agree with smart-case, which looks for any uppercase letter) before matching. The returned
+/// ranges are still into the original lines, even where lowercasing changed a character's length.
+pub(crate) fn find_matches(
+    lines: &[String],
+    line_starts: &[usize],
+    query: &str,
+    case_insensitive: bool,
+) -> Vec<(usize, Range<usize>)> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let query = if case_insensitive {
+        to_lowercase_with_offsets(query).0
+    } else {
+        query.to_owned()
+    };
+
+    let mut matches = Vec::new();
+    let mut first = 0;
+    while first < lines.len() {
+        let end = first
+            + 1
+            + line_starts
+                .get(first + 1..lines.len())
+                .unwrap_or_default()
+                .iter()
+                .take_while(|&&start| start != 0)
+                .count();
+        let starts = line_starts.get(first..end).unwrap_or_default();
+
+        // Put the page's line back together, filling in the whitespace wrapping removed
+        let mut line = String::new();
+        for (part, &start) in lines[first..end].iter().zip(starts) {
+            let gap = start.saturating_sub(line.len());
+            line.extend(iter::repeat_n(' ', gap));
+            line.push_str(part);
+        }
+        let lowercase = case_insensitive.then(|| to_lowercase_with_offsets(&line));
+        let searched = lowercase.as_ref().map_or(&line, |(lowercase, _)| lowercase);
+
+        for (byte_offset, found) in searched.match_indices(query.as_str()) {
+            // Map the match back onto the characters of the original line it came from
+            let range = match &lowercase {
+                Some((_, offsets)) => {
+                    let last = offsets[byte_offset + found.len() - 1];
+                    let last_len = line[last..].chars().next().map_or(0, char::len_utf8);
+                    offsets[byte_offset]..last + last_len
+                }
+                None => byte_offset..byte_offset + found.len(),
+            };
+            let part = starts
+                .partition_point(|&start| start <= range.start)
+                .saturating_sub(1);
+            let line_index = first + part;
+            let start = starts.get(part).copied().unwrap_or_default();
+            matches.push((
+                line_index,
+                (range.start - start).min(lines[line_index].len())..range.end - start,
+            ));
+        }
+
+        first = end;
+    }
+
+    matches
+}
+
+/// Lowercases `text` character by character, also returning the byte offset in `text` of the
+/// character each byte of the lowercased text came from.
+fn to_lowercase_with_offsets(text: &str) -> (String, Vec<usize>) {
+    let mut lowercase = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len());
+    for (offset, c) in text.char_indices() {
+        let start = lowercase.len();
+        lowercase.extend(c.to_lowercase());
+        offsets.extend(iter::repeat_n(offset, lowercase.len() - start));
+    }
+
+    (lowercase, offsets)
+}
+
+/// Patches `style` onto the portion of `line` covered by the byte `range`, splitting spans as
+/// needed. Ranges (or range ends) that don't fall on a `char` boundary of the line's spans are
+/// ignored rather than panicking.
+pub(crate) fn patch_line_style(line: &mut Line<'_>, range: Range<usize>, style: Style) {
+    let mut spans = Vec::with_capacity(line.spans.len() + 2);
+    let mut span_start = 0;
+
+    for span in line.spans.drain(..) {
+        let span_end = span_start + span.content.len();
+        let start = range.start.clamp(span_start, span_end) - span_start;
+        let end = range.end.clamp(span_start, span_end) - span_start;
+        span_start = span_end;
+
+        if start == end
+            || !span.content.is_char_boundary(start)
+            || !span.content.is_char_boundary(end)
+        {
+            spans.push(span);
+            continue;
+        }
+
+        let (before, rest) = span.content.split_at(start);
+        let (patched, after) = rest.split_at(end - start);
+        if !before.is_empty() {
+            spans.push(Span::styled(before.to_owned(), span.style));
+        }
+        spans.push(Span::styled(patched.to_owned(), span.style.patch(style)));
+        if !after.is_empty() {
+            spans.push(Span::styled(after.to_owned(), span.style));
+        }
+    }
+
+    line.spans = spans;
+}
+
+/// Parses the (ANSI-formatted) man page `content` into a styled [`Text`], soft-wrapping every line
+/// to `wrap_width` columns if one is given.
+///
+/// Along with the text, returns the byte offset each of its lines starts at in the line of the page
+/// it was wrapped from. This is 0 for the first (or only) line wrapped from each line of the page,
+/// and more for the rest.
+pub(crate) fn format_content(
+    content: &str,
+    wrap_width: Option<usize>,
+) -> Result<(Text<'static>, Vec<usize>)> {
+    let text = content.into_text()?;
+
+    Ok(match wrap_width {
+        Some(width) => {
+            let (lines, line_starts): (Vec<_>, Vec<_>) = text
+                .lines
+                .iter()
+                .flat_map(|line| wrap_line(line, width))
+                .unzip();
+            (lines.into(), line_starts)
+        }
+        None => {
+            let line_starts = vec![0; text.lines.len()];
+            (text, line_starts)
+        }
+    })
+}
+
+/// Removes all colors from `text`, leaving its other styling (e.g. bold and underline).
+pub(crate) fn remove_colors(text: &mut Text<'_>) {
+    let without_colors = |style: &mut Style| {
+        style.fg = None;
+        style.bg = None;
+        style.underline_color = None;
+    };
+
+    without_colors(&mut text.style);
+    for line in &mut text.lines {
+        without_colors(&mut line.style);
+        for span in &mut line.spans {
+            without_colors(&mut span.style);
+        }
+    }
+}
+
+/// Returns the unstyled contents of each line in `text`.
+pub(crate) fn plain_lines(text: &Text<'_>) -> Vec<String> {
+    text.lines.iter().map(Line::to_string).collect()
+}
+
+/// Soft-wraps `line` to `width` columns (with [`textwrap::wrap`]), keeping the styling of its
+/// spans. Each wrapped line comes with the byte offset in `line` that it starts at.
+fn wrap_line(line: &Line<'_>, width: usize) -> Vec<(Line<'static>, usize)> {
+    let plain = line.to_string();
+    let mut search_from = 0;
+
+    textwrap::wrap(&plain, width)
+        .iter()
+        .map(|wrapped| {
+            // Wrapped lines are usually borrowed from `plain`, in which case their offset can be
+            // computed directly. Otherwise, find them after the end of the previous wrapped line.
+            let start = match wrapped {
+                Cow::Borrowed(slice) => slice.as_ptr() as usize - plain.as_ptr() as usize,
+                Cow::Owned(owned) => plain[search_from..]
+                    .find(owned.as_str())
+                    .map_or(search_from, |offset| search_from + offset),
+            };
+            search_from = start + wrapped.len();
+
+            (slice_line(line, start..search_from), start)
+        })
+        .collect()
+}
+
+/// Returns the portion of `line` covered by the byte `range`, keeping the styling of its spans.
+fn slice_line(line: &Line<'_>, range: Range<usize>) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut span_start = 0;
+
+    for span in &line.spans {
+        let span_end = span_start + span.content.len();
+        let start = range.start.clamp(span_start, span_end) - span_start;
+        let end = range.end.clamp(span_start, span_end) - span_start;
+        span_start = span_end;
+
+        if let Some(content) = span.content.get(start..end).filter(|s| !s.is_empty()) {
+            spans.push(Span::styled(content.to_owned(), span.style));
+        }
+    }
+
+    Line::from(spans).style(line.style)
+}
+
+/// Returns the byte ranges of the words in `line` (delimited by whitespace and `/`, as in
+/// [`word_at_position`]) that parse as [`ManPageInfo`]s, excluding any trailing punctuation and
+/// enclosing parentheses (see [`strip_enclosing_parens`]).
+pub(crate) fn find_man_refs(line: &str) -> Vec<Range<usize>> {
+    let mut refs = Vec::new();
+    let mut word_start = 0;
+
+    for (index, c) in line.char_indices().chain(iter::once((line.len(), ' '))) {
+        if !c.is_whitespace() && c != '/' {
+            continue;
+        }
+
+        let word = strip_enclosing_parens(line, word_start..index);
+        if !word.is_empty() && ManPageInfo::try_from(&line[word.clone()]).is_ok() {
+            refs.push(word);
+        }
+        word_start = index + c.len_utf8();
+    }
+
+    refs
+}
+
+/// Parses the output of `man -f` (`whatis`) or `man -k` (`apropos`) into the `(name, section,
+/// description)` of each page listed, skipping repeated pages.
+pub(crate) fn parse_man_listing(output: &str) -> Vec<(String, String, String)> {
+    let mut entries: Vec<(String, String, String)> = Vec::new();
+
+    // Entries look like `printf (3)           - formatted output conversion`, or (from mandoc)
+    // `printf, fprintf(3) - formatted output conversion` for several pages at once
+    for line in output.lines() {
+        let (pages, description) = line.split_once(" - ").unwrap_or((line, ""));
+        let Some((names, section_number)) = pages.trim().split_once('(') else {
+            continue;
+        };
+        let Some(section_number) = section_number.strip_suffix(')') else {
+            continue;
+        };
+
+        for name in names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            if !entries
+                .iter()
+                .any(|(entry_name, section, _)| entry_name == name && section == section_number)
+            {
+                entries.push((
+                    name.to_owned(),
+                    section_number.to_owned(),
+                    description.trim().to_owned(),
+                ));
+            }
+        }
+    }
+
+    entries
+}
+
+/// Parses the output of `man -f` (`whatis`) into the `(section, description)` of each entry for
+/// the page `name`, skipping entries for other pages and repeated sections.
+pub(crate) fn parse_whatis(output: &str, name: &str) -> Vec<(String, String)> {
+    parse_man_listing(output)
+        .into_iter()
+        .filter(|(entry_name, _, _)| entry_name == name)
+        .map(|(_, section_number, description)| (section_number, description))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|&line| line.to_owned()).collect()
+    }
+
+    /// Returns the word [`word_bounds`] finds around grapheme `col` of `line`.
+    fn word_around(line: &str, col: usize) -> Option<String> {
+        let graphemes: Vec<&str> = line.graphemes(true).collect();
+        word_bounds(&graphemes, col).map(|(start, end)| graphemes[start..end].concat())
+    }
+
+    /// Returns the text of each man reference [`find_man_refs`] finds in `line`.
+    fn refs_in(line: &str) -> Vec<&str> {
+        find_man_refs(line)
+            .into_iter()
+            .map(|range| &line[range])
+            .collect()
+    }
+
+    #[test]
+    fn man_refs_by_section() {
+        assert_eq!(
+            refs_in("see foo(3p), bar(1ssl) and baz(3tiff), or open(2)."),
+            ["foo(3p)", "bar(1ssl)", "baz(3tiff)", "open(2)"]
+        );
+    }
+
+    #[test]
+    fn man_refs_with_dots_and_hyphens() {
+        assert_eq!(
+            refs_in("resolv.conf(5) git-commit(1) systemd.service(5)."),
+            ["resolv.conf(5)", "git-commit(1)", "systemd.service(5)"]
+        );
+    }
+
+    #[test]
+    fn no_false_positive_man_refs() {
+        assert!(refs_in("errno(3)))").is_empty());
+        assert!(refs_in("a signal(7)-style handler").is_empty());
+        assert!(refs_in("call printf(\"%d\") or f(x)").is_empty());
+        assert!(refs_in("(2) and    (2)").is_empty());
+    }
+
+    #[test]
+    fn see_also_block() {
+        let see_also = [
+            "SEE ALSO",
+            "       getaddrinfo(3), gethostbyname(3), resolv.conf(5),",
+            "       hosts(5), nsswitch.conf(5)",
+        ];
+        assert!(refs_in(see_also[0]).is_empty());
+        assert_eq!(
+            refs_in(see_also[1]),
+            ["getaddrinfo(3)", "gethostbyname(3)", "resolv.conf(5)"]
+        );
+        assert_eq!(refs_in(see_also[2]), ["hosts(5)", "nsswitch.conf(5)"]);
+    }
+
+    #[test]
+    fn word_bounds_within_words() {
+        assert_eq!(word_around("hello world", 0).as_deref(), Some("hello"));
+        assert_eq!(word_around("hello world", 2).as_deref(), Some("hello"));
+        assert_eq!(word_around("hello world", 4).as_deref(), Some("hello"));
+        assert_eq!(word_around("hello world", 6).as_deref(), Some("world"));
+        assert_eq!(word_around("hello world", 10).as_deref(), Some("world"));
+    }
+
+    #[test]
+    fn word_bounds_on_whitespace() {
+        assert_eq!(word_around("hello world", 5), None);
+        assert_eq!(word_around("  indented", 0), None);
+        assert_eq!(word_around("hello", 5), None);
+    }
+
+    #[test]
+    fn word_bounds_around_slashes() {
+        assert_eq!(word_around("/usr/bin/ls", 0), None);
+        assert_eq!(word_around("/usr/bin/ls", 2).as_deref(), Some("usr"));
+        assert_eq!(word_around("/usr/bin/ls", 4).as_deref(), Some("usr"));
+        assert_eq!(word_around("/usr/bin/ls", 6).as_deref(), Some("bin"));
+        assert_eq!(word_around("/usr/bin/ls", 10).as_deref(), Some("ls"));
+    }
+
+    #[test]
+    fn word_bounds_around_parens() {
+        // The whole reference, from anywhere in its name
+        assert_eq!(word_around("see ls(1) now", 4).as_deref(), Some("ls(1)"));
+        assert_eq!(word_around("see ls(1) now", 5).as_deref(), Some("ls(1)"));
+        // Words start after a paren, but don't end at one
+        assert_eq!(word_around("see ls(1) now", 7).as_deref(), Some("1)"));
+        assert_eq!(word_around("(see ls(1))", 0).as_deref(), Some("(see"));
+        assert_eq!(word_around("(see ls(1))", 2).as_deref(), Some("see"));
+        assert_eq!(word_around("(see ls(1))", 6).as_deref(), Some("ls(1))"));
+    }
+
+    #[test]
+    fn words_at_wide_characters_and_tabs() {
+        // Each CJK character takes two columns, so `foo(1)` starts at the 6th
+        let cjk = lines(&["漢字 foo(1)"]);
+        assert_eq!(word_at_position(&cjk, 0, 1, 6), Some("foo(1)"));
+        assert_eq!(word_at_position(&cjk, 0, 1, 8), Some("foo(1)"));
+        assert_eq!(word_at_position(&cjk, 0, 1, 4), Some("漢字"));
+        assert_eq!(word_at_position(&cjk, 0, 1, 5), None);
+
+        let tab = lines(&["\tfoo(1)"]);
+        assert_eq!(word_at_position(&tab, 0, 1, 3), None);
+        assert_eq!(word_at_position(&tab, 0, 1, 9), Some("foo(1)"));
+    }
+
+    #[test]
+    fn words_leave_out_enclosing_parens() {
+        let see = lines(&["(see signal(7)) and ((open(2)))."]);
+        assert_eq!(word_at_position(&see, 0, 1, 6), Some("signal(7)"));
+        assert_eq!(word_at_position(&see, 0, 1, 24), Some("open(2)"));
+    }
+
+    #[test]
+    fn man_refs_in_parens() {
+        assert_eq!(refs_in("(see signal(7))"), ["signal(7)"]);
+        assert_eq!(refs_in("((open(2)))"), ["open(2)"]);
+        // Unless there's a stray `)` after the reference
+        assert!(refs_in("(errno(3)))").is_empty());
+
+        let see = lines(&["(see signal(7))", "(open(2))."]);
+        assert_eq!(man_refs_at_position(&see, 0, 1, 6), ["signal(7)"]);
+        assert_eq!(man_refs_at_position(&see, 0, 2, 2), ["open(2)"]);
+    }
+
+    #[test]
+    fn columns_past_the_end() {
+        assert_eq!(grapheme_at_column(&["漢", "字"], 3), Some(1));
+        assert_eq!(grapheme_at_column(&["漢", "字"], 4), None);
+        assert_eq!(grapheme_at_column(&["a", "\t", "b"], 7), Some(1));
+        assert_eq!(grapheme_at_column(&["a", "\t", "b"], 8), Some(2));
+    }
+
+    #[test]
+    fn man_strings() {
+        assert_eq!(
+            get_man_string("MOUNT(2)     Linux Programmer's Manual     MOUNT(2)\n").unwrap(),
+            "MOUNT(2)"
+        );
+        assert_eq!(get_man_string("foo").unwrap(), "foo");
+        assert_eq!(get_man_string("\u{feff}foo(1)\nbar").unwrap(), "foo(1)");
+        assert_eq!(get_man_string("  \n  foo(1) ").unwrap(), "foo(1)");
+        assert!(get_man_string("").is_err());
+        assert!(get_man_string(" \n\t").is_err());
+        assert!(get_man_string("\u{feff}").is_err());
+    }
+
+    #[test]
+    fn see_also_references_wrapped_across_lines() {
+        let hyphenated = lines(&["       getaddr\u{2010}", "       info(3), getnameinfo(3)"]);
+        assert_eq!(
+            man_refs_at_position(&hyphenated, 0, 1, 10),
+            ["getaddrinfo(3)"]
+        );
+        assert_eq!(
+            man_refs_at_position(&hyphenated, 0, 2, 9),
+            ["getaddrinfo(3)"]
+        );
+        assert_eq!(
+            man_refs_at_position(&hyphenated, 0, 2, 20),
+            ["getnameinfo(3)"]
+        );
+
+        let split_section = lines(&["       see intro", "       (2), first"]);
+        assert_eq!(man_refs_at_position(&split_section, 0, 1, 14), ["intro(2)"]);
+        assert_eq!(man_refs_at_position(&split_section, 0, 2, 9), ["intro(2)"]);
+        assert!(man_refs_at_position(&split_section, 0, 2, 14).is_empty());
+    }
+
+    #[test]
+    fn subcommand_references() {
+        let see_also = lines(&["See git commit(1) and systemctl status(1)."]);
+        assert_eq!(
+            man_refs_at_position(&see_also, 0, 1, 10),
+            ["git-commit(1)", "commit(1)"]
+        );
+        assert_eq!(
+            man_refs_at_position(&see_also, 0, 1, 34),
+            ["systemctl-status(1)", "status(1)"]
+        );
+
+        // Ordinary words before a reference aren't tools
+        let prose = lines(&["See The commit(1) page"]);
+        assert_eq!(man_refs_at_position(&prose, 0, 1, 10), ["commit(1)"]);
+    }
+
+    #[test]
+    fn matches_ignoring_case() {
+        let page = lines(&["Open the OPEN file", "İx"]);
+        let starts = [0, 0];
+        assert_eq!(
+            find_matches(&page, &starts, "open", true),
+            [(0, 0..4), (0, 9..13)]
+        );
+        assert_eq!(find_matches(&page, &starts, "open", false), []);
+        // `İ` lowercases to more bytes than it has, which the offsets account for
+        assert_eq!(find_matches(&page, &starts, "x", true), [(1, 2..3)]);
+    }
+
+    #[test]
+    fn matches_of_characters_that_change_length_when_lowercased() {
+        // The Kelvin sign takes three bytes, but its lowercase `k` only takes one
+        let page = lines(&["0 \u{212A}elvin", "İstanbul"]);
+        let starts = [0, 0];
+        assert_eq!(find_matches(&page, &starts, "kelvin", true), [(0, 2..10)]);
+        assert_eq!(find_matches(&page, &starts, "0 k", true), [(0, 0..5)]);
+        // `İ` lowercases to `i` and a combining dot, so matching either covers all of it
+        assert_eq!(
+            find_matches(&page, &starts, "i", true),
+            [(0, 8..9), (1, 0..2)]
+        );
+        assert_eq!(
+            find_matches(&page, &starts, "\u{307}stan", true),
+            [(1, 0..6)]
+        );
+    }
+
+    #[test]
+    fn matches_across_wrapped_lines() {
+        // "hello world", wrapped after "hello"
+        let page = lines(&["hello", "world"]);
+        assert_eq!(find_matches(&page, &[0, 6], "lo wo", false), [(0, 3..8)]);
+        assert_eq!(find_matches(&page, &[0, 6], "world", false), [(1, 0..5)]);
+        assert_eq!(find_matches(&page, &[0, 6], "LO WO", true), [(0, 3..8)]);
+        // Unless they were separate lines of the page to begin with
+        assert_eq!(find_matches(&page, &[0, 0], "lo wo", false), []);
+    }
+
+    #[test]
+    fn man_listings() {
+        let output = "\
+printf (3)           - formatted output conversion
+printf (1)           - format and print data
+printf, fprintf(3) - formatted output conversion
+not a listing
+";
+        assert_eq!(
+            parse_man_listing(output),
+            [
+                ("printf", "3", "formatted output conversion"),
+                ("printf", "1", "format and print data"),
+                ("fprintf", "3", "formatted output conversion"),
+            ]
+            .map(|(name, section, description)| (
+                name.to_owned(),
+                section.to_owned(),
+                description.to_owned()
+            ))
+        );
+
+        assert_eq!(
+            parse_whatis(output, "printf"),
+            [
+                (
+                    String::from("3"),
+                    String::from("formatted output conversion")
+                ),
+                (String::from("1"), String::from("format and print data")),
+            ]
+        );
+    }
+}